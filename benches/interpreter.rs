@@ -0,0 +1,154 @@
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use satukitan_rs::parser::parse_program;
+use satukitan_rs::{Backend, Interpreter};
+
+const NUMERALS: [&str; 11] = [
+    "rv", "ru", "ra", "ro", "re", "ri", "rya", "ryu", "ryo", "rye", "#ta",
+];
+
+const FIBO: &str = r#"
+gakasdenu fibo (n) (
+    nobu (ditasgata n ru)
+        (n)
+        (ritas (fibo (matyes n ru)) (fibo (matyes n ra)))
+)
+"#;
+
+/// A program with `count` function definitions, each called once.
+fn large_program(count: usize) -> String {
+    let mut source = String::new();
+    for idx in 0..count {
+        let a = NUMERALS[idx % NUMERALS.len()];
+        let b = NUMERALS[(idx * 7) % NUMERALS.len()];
+        source.push_str(&format!(
+            "# definition {idx}\ngakasdenu f-{idx} (x y) (\n    nobu (ditas x y)\n        (ritas x y [{a} {b}])\n        (matyes x y)\n)\nsipus (f-{idx} {a} {b})\n"
+        ));
+    }
+    source
+}
+
+/// A list literal of `len` pseudo-random numerals.
+fn unsorted_list(len: usize) -> String {
+    let mut state = 0x2545_f491_u32;
+    let items = (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            NUMERALS[state as usize % NUMERALS.len()]
+        })
+        .collect::<Vec<_>>();
+    format!("fanitas [{}]", items.join(" "))
+}
+
+/// `depth` nested closures whose innermost body reads the outermost
+/// parameter and a global.
+fn nested_closures(depth: usize) -> String {
+    let mut source = String::from("gakas base ru\n");
+    for level in 0..depth {
+        let open = if level == 0 { "" } else { "(" };
+        source.push_str(&format!("{open}gakasdenu f{level} (a{level}) (\n"));
+    }
+    source.push_str("(ritas a0 base)\n");
+    for level in (1..depth).rev() {
+        source.push_str(&format!("))\n(f{level} ru)\n"));
+    }
+    source.push_str(")\n");
+    source
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_program");
+    for count in [100, 1000] {
+        let source = large_program(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &source, |b, source| {
+            b.iter(|| parse_program(black_box(source)).expect("parse failed"))
+        });
+    }
+    group.finish();
+}
+
+fn fibo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fibo");
+    for (name, backend) in [("tree", Backend::TreeWalk), ("vm", Backend::Bytecode)] {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter.eval_str(FIBO).expect("definition failed");
+        let call = parse_program("fibo (ritas ri #ta)").expect("parse failed");
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                interpreter
+                    .eval_program(black_box(&call))
+                    .expect("fibo failed")
+            })
+        });
+    }
+    group.finish();
+}
+
+fn sort(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fanitas");
+    for len in [100, 10_000] {
+        let program = parse_program(&unsorted_list(len)).expect("parse failed");
+        let mut interpreter = Interpreter::new();
+        group.bench_with_input(BenchmarkId::from_parameter(len), &program, |b, program| {
+            b.iter(|| {
+                interpreter
+                    .eval_program(black_box(program))
+                    .expect("sort failed")
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Reads a global list of `len` numbers from a function called 100 times.
+const LIST_READS: &str = r#"
+gakasdenu walk (n) (
+    nobu (ditasgata n rv)
+        (rv)
+        (ritas (rakas xs) (walk (matyes n ru)))
+)
+"#;
+
+fn list_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_reads");
+    for (len, numeral) in [(100, "nitas #ta #ta"), (10_000, "nitas #ta #ta #ta #ta")] {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str(&format!("gakas xs (range rv ({numeral}))\n{LIST_READS}"))
+            .expect("definition failed");
+        let call = parse_program("walk (nitas #ta #ta)").expect("parse failed");
+        group.bench_with_input(BenchmarkId::from_parameter(len), &call, |b, call| {
+            b.iter(|| {
+                interpreter
+                    .eval_program(black_box(call))
+                    .expect("walk failed")
+            })
+        });
+    }
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_lookup");
+    for depth in [4, 32] {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str(&nested_closures(depth))
+            .expect("definition failed");
+        let call = parse_program("f0 ra").expect("parse failed");
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &call, |b, call| {
+            b.iter(|| {
+                interpreter
+                    .eval_program(black_box(call))
+                    .expect("lookup failed")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, fibo, sort, list_reads, lookup);
+criterion_main!(benches);
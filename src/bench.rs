@@ -0,0 +1,68 @@
+#[cfg(feature = "alloc-stats")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts allocations made through it.
+///
+/// The `satukitan` binary registers it when built with the `alloc-stats`
+/// feature so `satukitan bench` can report allocation counts. Without the
+/// feature [`measure`] reports no allocation counts.
+#[cfg(feature = "alloc-stats")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "alloc-stats")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Measurement {
+    pub elapsed: Duration,
+    /// `None` unless built with the `alloc-stats` feature.
+    pub allocs: Option<AllocStats>,
+}
+
+fn snapshot() -> AllocStats {
+    AllocStats {
+        count: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Runs `f` once, recording its wall time and the allocations it made.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Measurement) {
+    let before = snapshot();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let after = snapshot();
+    let allocs = cfg!(feature = "alloc-stats").then(|| AllocStats {
+        count: after.count - before.count,
+        bytes: after.bytes - before.bytes,
+    });
+    (result, Measurement { elapsed, allocs })
+}
@@ -1,21 +1,133 @@
+use std::cell::Cell;
+use std::fs;
+use std::io::Write;
+use std::rc::Rc;
+
 use crate::env::Environment;
 use crate::error::SatukitanError;
-use crate::value::{Arity, Value};
+use crate::io::{FsAccess, Input, Output};
+use crate::json;
+use crate::limits::Limits;
+use crate::value::{Arity, BuiltinFunction, Value};
+
+/// Builtins without side effects, which the optimiser may evaluate ahead of time.
+pub const PURE: &[&str] = &[
+    "ritas",
+    "matyes",
+    "nitas",
+    "teses",
+    "kenus",
+    "ditas",
+    "fityes",
+    "gatas",
+    "ditasgata",
+    "fityesgata",
+    "fanitas",
+    "rakas",
+    "head",
+    "tail",
+    "cons",
+    "sum",
+    "json-parse",
+    "json-stringify",
+];
 
 pub fn install(env: &mut Environment) {
-    env.define_builtin("ritas", Arity::AtLeast(2), builtin_add);
-    env.define_builtin("matyes", Arity::AtLeast(2), builtin_sub);
-    env.define_builtin("nitas", Arity::AtLeast(2), builtin_mul);
-    env.define_builtin("teses", Arity::AtLeast(2), builtin_and);
-    env.define_builtin("kenus", Arity::AtLeast(2), builtin_or);
-    env.define_builtin("ditas", Arity::Exact(2), builtin_lt);
-    env.define_builtin("fityes", Arity::Exact(2), builtin_gt);
-    env.define_builtin("gatas", Arity::AtLeast(2), builtin_eq);
-    env.define_builtin("ditasgata", Arity::Exact(2), builtin_le);
-    env.define_builtin("fityesgata", Arity::Exact(2), builtin_ge);
-    env.define_builtin("fanitas", Arity::Exact(1), builtin_sort);
-    env.define_builtin("rakas", Arity::Exact(1), builtin_length);
-    env.define_builtin("sipus", Arity::Any, builtin_print);
+    define_pure(env, "ritas", Arity::AtLeast(2), builtin_add);
+    define_pure(env, "matyes", Arity::AtLeast(2), builtin_sub);
+    define_pure(env, "nitas", Arity::AtLeast(2), builtin_mul);
+    define_pure(env, "teses", Arity::AtLeast(2), builtin_and);
+    define_pure(env, "kenus", Arity::AtLeast(2), builtin_or);
+    define_pure(env, "ditas", Arity::Exact(2), builtin_lt);
+    define_pure(env, "fityes", Arity::Exact(2), builtin_gt);
+    define_pure(env, "gatas", Arity::AtLeast(2), builtin_eq);
+    define_pure(env, "ditasgata", Arity::Exact(2), builtin_le);
+    define_pure(env, "fityesgata", Arity::Exact(2), builtin_ge);
+    define_pure(env, "fanitas", Arity::Exact(1), builtin_sort);
+    define_pure(env, "rakas", Arity::Exact(1), builtin_length);
+    define_pure(env, "head", Arity::Exact(1), builtin_head);
+    define_pure(env, "tail", Arity::Exact(1), builtin_tail);
+    define_pure(env, "cons", Arity::Exact(2), builtin_cons);
+    define_pure(env, "sum", Arity::Exact(1), builtin_sum);
+    define_pure(env, "json-parse", Arity::Exact(1), builtin_json_parse);
+    define_pure(
+        env,
+        "json-stringify",
+        Arity::Exact(1),
+        builtin_json_stringify,
+    );
+}
+
+/// Installs the builtins that build lists of a caller-chosen size, checking
+/// that size against the current `limits` before allocating.
+pub fn install_lists(env: &mut Environment, limits: &Rc<Cell<Limits>>) {
+    let limits = limits.clone();
+    env.define_builtin("range", Arity::Exact(2), move |args| {
+        builtin_range(limits.get().max_list_len, args)
+    });
+}
+
+/// The builtins that talk to the host process: `getenv` and `exit`. Only
+/// installed when the embedder allows it.
+pub fn process_builtins() -> [BuiltinFunction; 2] {
+    [
+        BuiltinFunction::new("getenv", Arity::Exact(1), builtin_getenv),
+        BuiltinFunction::new("exit", Arity::Any, builtin_exit),
+    ]
+}
+
+/// Installs the builtins that read from `input`. `lines-iterator` returns a
+/// builtin yielding the next line on each call; like `read-line` and
+/// `read-all` it returns `nil` at end of input.
+pub fn install_input(env: &mut Environment, input: &Input) {
+    let reader = input.clone();
+    env.define_builtin("read-line", Arity::Exact(0), move |_| next_line(&reader));
+    let reader = input.clone();
+    env.define_builtin("read-all", Arity::Exact(0), move |_| {
+        Ok(reader.read_all()?.map_or(Value::Nil, Value::from))
+    });
+    let reader = input.clone();
+    env.define_builtin("lines-iterator", Arity::Exact(0), move |_| {
+        let reader = reader.clone();
+        let next = BuiltinFunction::new("next-line", Arity::Exact(0), move |_| next_line(&reader));
+        Ok(Value::Builtin(next))
+    });
+}
+
+/// Installs the filesystem builtins, which only work while `access` is enabled.
+pub fn install_filesystem(env: &mut Environment, access: &FsAccess) {
+    let fs_builtins: [(&str, Arity, FsBuiltin); 5] = [
+        ("read-file", Arity::Exact(1), builtin_read_file),
+        ("write-file", Arity::Exact(2), builtin_write_file),
+        ("append-file", Arity::Exact(2), builtin_append_file),
+        ("list-dir", Arity::Exact(1), builtin_list_dir),
+        ("file-exists", Arity::Exact(1), builtin_file_exists),
+    ];
+    for (name, arity, func) in fs_builtins {
+        let access = access.clone();
+        env.define_builtin(name, arity, move |args| {
+            access.check()?;
+            func(args)
+        });
+    }
+}
+
+fn define_pure(
+    env: &mut Environment,
+    name: &'static str,
+    arity: Arity,
+    func: fn(&[Value]) -> Result<Value, SatukitanError>,
+) {
+    let builtin = BuiltinFunction::new(name, arity, func).into_pure();
+    env.define(name, Value::Builtin(builtin));
+}
+
+/// Installs the builtins that write to `output`.
+pub fn install_output(env: &mut Environment, output: &Output) {
+    let output = output.clone();
+    env.define_builtin("sipus", Arity::Any, move |args| {
+        builtin_print(&output, args)
+    });
 }
 
 fn builtin_add(args: &[Value]) -> Result<Value, SatukitanError> {
@@ -119,20 +231,149 @@ fn builtin_length(args: &[Value]) -> Result<Value, SatukitanError> {
     Ok(Value::Number(items.len() as i64))
 }
 
-fn builtin_print(args: &[Value]) -> Result<Value, SatukitanError> {
-    if args.is_empty() {
-        println!();
-    } else {
-        let output = args
-            .iter()
-            .map(|value| value.to_string())
-            .collect::<Vec<_>>()
-            .join(" ");
-        println!("{}", output);
+fn builtin_head(args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("head", args, 1)?;
+    match expect_list("head", &args[0])? {
+        [first, ..] => Ok(first.clone()),
+        [] => Err(SatukitanError::Eval("head: empty list".to_string())),
     }
+}
+
+fn builtin_tail(args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("tail", args, 1)?;
+    match expect_list("tail", &args[0])? {
+        [_, rest @ ..] => Ok(Value::List(rest.into())),
+        [] => Err(SatukitanError::Eval("tail: empty list".to_string())),
+    }
+}
+
+/// Prepends a value to a list.
+fn builtin_cons(args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("cons", args, 2)?;
+    let items = expect_list("cons", &args[1])?;
+    let mut list = Vec::with_capacity(items.len() + 1);
+    list.push(args[0].clone());
+    list.extend_from_slice(items);
+    Ok(Value::from(list))
+}
+
+/// Adds up a list of numbers, `rv` for an empty list. Fails rather than
+/// wrapping when the total does not fit.
+fn builtin_sum(args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("sum", args, 1)?;
+    let mut sum = 0i64;
+    for value in expect_list("sum", &args[0])? {
+        sum = sum
+            .checked_add(expect_number("sum", value)?)
+            .ok_or_else(|| SatukitanError::Eval("sum: integer overflow".into()))?;
+    }
+    Ok(Value::Number(sum))
+}
+
+/// Numbers from the first argument up to but not including the second.
+/// Fails without allocating when there would be more than `max_len`.
+fn builtin_range(max_len: Option<usize>, args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("range", args, 2)?;
+    let from = expect_number("range", &args[0])?;
+    let to = expect_number("range", &args[1])?;
+    let len = usize::try_from((i128::from(to) - i128::from(from)).max(0)).unwrap_or(usize::MAX);
+    if let Some(limit) = max_len
+        && len > limit
+    {
+        return Err(SatukitanError::AllocationLimitExceeded {
+            kind: "list",
+            len,
+            limit,
+        });
+    }
+    Ok(Value::List((from..to).map(Value::Number).collect()))
+}
+
+fn builtin_print(output: &Output, args: &[Value]) -> Result<Value, SatukitanError> {
+    let line = args
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    output.write_line(&line)?;
     Ok(Value::Nil)
 }
 
+fn builtin_json_parse(args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("json-parse", args, 1)?;
+    match &args[0] {
+        Value::String(text) => json::parse(text),
+        other => Err(SatukitanError::type_mismatch("string", other.type_name())),
+    }
+}
+
+fn builtin_json_stringify(args: &[Value]) -> Result<Value, SatukitanError> {
+    ensure_exact("json-stringify", args, 1)?;
+    json::stringify(&args[0]).map(Value::from)
+}
+
+/// Returns the variable's value, or `nil` when it is unset or not Unicode.
+fn builtin_getenv(args: &[Value]) -> Result<Value, SatukitanError> {
+    let name = expect_string("getenv", &args[0])?;
+    Ok(std::env::var(name).map_or(Value::Nil, Value::from))
+}
+
+/// Stops the script with the given status code, 0 by default.
+fn builtin_exit(args: &[Value]) -> Result<Value, SatukitanError> {
+    let code = match args {
+        [] => 0,
+        [code] => expect_number("exit", code)?,
+        _ => return Err(SatukitanError::arity_exact("exit", 1, args.len())),
+    };
+    let code = u8::try_from(code)
+        .map_err(|_| SatukitanError::Eval(format!("exit: code {code} is not in 0..=255")))?;
+    Err(SatukitanError::Exit(code))
+}
+
+fn next_line(input: &Input) -> Result<Value, SatukitanError> {
+    Ok(input.read_line()?.map_or(Value::Nil, Value::from))
+}
+
+type FsBuiltin = fn(&[Value]) -> Result<Value, SatukitanError>;
+
+fn builtin_read_file(args: &[Value]) -> Result<Value, SatukitanError> {
+    let path = expect_string("read-file", &args[0])?;
+    Ok(Value::from(fs::read_to_string(path)?))
+}
+
+fn builtin_write_file(args: &[Value]) -> Result<Value, SatukitanError> {
+    let path = expect_string("write-file", &args[0])?;
+    let contents = expect_string("write-file", &args[1])?;
+    fs::write(path, contents)?;
+    Ok(Value::Nil)
+}
+
+fn builtin_append_file(args: &[Value]) -> Result<Value, SatukitanError> {
+    let path = expect_string("append-file", &args[0])?;
+    let contents = expect_string("append-file", &args[1])?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(contents.as_bytes())?;
+    Ok(Value::Nil)
+}
+
+/// Lists the entry names of a directory in sorted order.
+fn builtin_list_dir(args: &[Value]) -> Result<Value, SatukitanError> {
+    let path = expect_string("list-dir", &args[0])?;
+    let mut names = fs::read_dir(path)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    names.sort();
+    Ok(Value::List(names.into_iter().map(Value::from).collect()))
+}
+
+fn builtin_file_exists(args: &[Value]) -> Result<Value, SatukitanError> {
+    let path = expect_string("file-exists", &args[0])?;
+    Ok(Value::Bool(fs::exists(path)?))
+}
+
 fn ensure_exact(name: &str, args: &[Value], expected: usize) -> Result<(), SatukitanError> {
     if args.len() == expected {
         Ok(())
@@ -177,9 +418,19 @@ fn expect_bool(name: &str, value: &Value) -> Result<bool, SatukitanError> {
     }
 }
 
+fn expect_string<'a>(name: &str, value: &'a Value) -> Result<&'a str, SatukitanError> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(SatukitanError::Eval(format!(
+            "{name}: expected string, found {}",
+            other.type_name()
+        ))),
+    }
+}
+
 fn expect_list<'a>(name: &str, value: &'a Value) -> Result<&'a [Value], SatukitanError> {
     match value {
-        Value::List(items) => Ok(items.as_slice()),
+        Value::List(items) => Ok(items),
         other => Err(SatukitanError::Eval(format!(
             "{name}: expected list, found {}",
             other.type_name()
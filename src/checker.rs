@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::ast::Expr;
+use crate::cst::{Node, NodeKind, Span, SyntaxTree};
+use crate::env::Environment;
+use crate::error::SatukitanError;
+use crate::resolver::{Malformed, SPECIAL_FORMS, collect_definitions, extract_body};
+use crate::value::{Arity, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    /// The program fails when the expression runs.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found by [`check_program`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Index of the top-level expression the problem is in.
+    pub expr: usize,
+    /// The identifier the problem is about: its name and how many times the
+    /// name occurs before it within the top-level expression.
+    pub subject: Option<(String, usize)>,
+}
+
+impl Diagnostic {
+    /// Where the diagnostic points in the tree the program was derived
+    /// from: its subject identifier, or else the whole top-level expression.
+    pub fn span(&self, tree: &SyntaxTree) -> Option<Span> {
+        let node = tree.nodes.get(self.expr)?;
+        let located = self.subject.as_ref().and_then(|(name, nth)| {
+            let mut seen = 0;
+            find_identifier(node, name, *nth, &mut seen)
+        });
+        Some(located.unwrap_or(node.span))
+    }
+}
+
+fn find_identifier(node: &Node, name: &str, nth: usize, seen: &mut usize) -> Option<Span> {
+    match &node.kind {
+        NodeKind::Identifier(text) if text == name => {
+            *seen += 1;
+            (*seen > nth).then_some(node.span)
+        }
+        NodeKind::Identifier(_) | NodeKind::String(_) => None,
+        NodeKind::Group { children, .. } => children
+            .iter()
+            .find_map(|child| find_identifier(child, name, nth, seen)),
+        NodeKind::Call { head, args } => std::iter::once(&**head)
+            .chain(args)
+            .find_map(|node| find_identifier(node, name, nth, seen)),
+    }
+}
+
+/// Finds mistakes in `program` without running it: undefined names, calls
+/// with the wrong number of arguments, malformed special forms, unused
+/// bindings and definitions shadowing builtins. `globals` holds the names
+/// available before the program runs, such as the builtins and prelude.
+///
+/// Unused parameters and local definitions are reported unless their name
+/// starts with `_`. At the top level only `_` names are reported, since the
+/// others are exported when the file is imported.
+pub fn check_program(program: &[Expr], globals: &Environment) -> Vec<Diagnostic> {
+    let globals = globals
+        .bindings()
+        .into_iter()
+        .map(|(name, value)| {
+            let kind = match value {
+                Value::Builtin(builtin) => Kind::Builtin(builtin.arity()),
+                Value::Function(function) => Kind::Function(function.params().len()),
+                _ => Kind::Value,
+            };
+            (name.as_str().to_string(), kind)
+        })
+        .collect();
+    let mut prefixes = Vec::new();
+    collect_imports(program, &mut prefixes);
+
+    let mut checker = Checker {
+        globals,
+        prefixes,
+        scopes: Vec::new(),
+        diagnostics: Vec::new(),
+        expr: 0,
+        occurrences: HashMap::new(),
+    };
+    checker.push_scope(Vec::new(), program);
+    for (idx, expr) in program.iter().enumerate() {
+        checker.expr = idx;
+        checker.occurrences.clear();
+        checker.visit(expr);
+    }
+    checker.pop_scope(true);
+    checker.diagnostics
+}
+
+/// What is known statically about a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Builtin(Arity),
+    /// A `gakasdenu` function with this many parameters.
+    Function(usize),
+    Value,
+}
+
+struct Binding {
+    name: String,
+    kind: Kind,
+    parameter: bool,
+    used: bool,
+    /// Where the binding is introduced, once the walk has reached it.
+    site: Option<(usize, usize)>,
+}
+
+struct Checker {
+    globals: HashMap<String, Kind>,
+    prefixes: Vec<String>,
+    scopes: Vec<Vec<Binding>>,
+    diagnostics: Vec<Diagnostic>,
+    expr: usize,
+    occurrences: HashMap<String, usize>,
+}
+
+impl Checker {
+    fn report(&mut self, severity: Severity, message: String, subject: Option<(String, usize)>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            expr: self.expr,
+            subject,
+        });
+    }
+
+    /// Counts an identifier at the current point of the walk.
+    fn occurrence(&mut self, name: &str) -> (String, usize) {
+        let count = self.occurrences.entry(name.to_string()).or_default();
+        *count += 1;
+        (name.to_string(), *count - 1)
+    }
+
+    /// Opens a scope holding `params` and the definitions made in `body`.
+    fn push_scope(&mut self, params: Vec<String>, body: &[Expr]) {
+        let mut defined = Vec::new();
+        collect_definitions(body, &mut defined);
+        let mut scope: Vec<Binding> = Vec::new();
+        for name in params {
+            scope.push(Binding {
+                name,
+                kind: Kind::Value,
+                parameter: true,
+                used: false,
+                site: None,
+            });
+        }
+        for name in defined {
+            let name = name.as_str();
+            if scope.iter().all(|binding| binding.name != name) {
+                scope.push(Binding {
+                    name: name.to_string(),
+                    kind: definition_kind(name, body),
+                    parameter: false,
+                    used: false,
+                    site: None,
+                });
+            }
+        }
+        self.scopes.push(scope);
+    }
+
+    fn pop_scope(&mut self, top_level: bool) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        for binding in scope {
+            // Top-level names are exported unless they start with `_`.
+            let private = binding.name.starts_with('_');
+            let reported = if top_level { private } else { !private };
+            if binding.used || !reported {
+                continue;
+            }
+            let Some((expr, nth)) = binding.site else {
+                continue;
+            };
+            let what = if binding.parameter {
+                "parameter"
+            } else {
+                "binding"
+            };
+            self.diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                message: format!("unused {what} '{}'", binding.name),
+                expr,
+                subject: Some((binding.name, nth)),
+            });
+        }
+    }
+
+    /// Records where a binding of the innermost scope is introduced and
+    /// warns if it hides a builtin or special form.
+    fn introduce(&mut self, name: &str) {
+        let subject = self.occurrence(name);
+        let site = (self.expr, subject.1);
+        if let Some(binding) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.iter_mut().find(|binding| binding.name == name))
+        {
+            binding.site.get_or_insert(site);
+        }
+        if SPECIAL_FORMS.contains(&name) {
+            let message = format!("'{name}' shadows a special form and is never called");
+            self.report(Severity::Warning, message, Some(subject));
+        } else if matches!(self.globals.get(name), Some(Kind::Builtin(_))) {
+            let message = format!("'{name}' shadows a builtin");
+            self.report(Severity::Warning, message, Some(subject));
+        }
+    }
+
+    /// What `name` refers to here, or `None` if it is undefined.
+    fn lookup(&self, name: &str) -> Option<Kind> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(binding) = scope.iter().find(|binding| binding.name == name) {
+                return Some(binding.kind);
+            }
+        }
+        if let Some(kind) = self.globals.get(name) {
+            return Some(*kind);
+        }
+        let imported = self.prefixes.iter().any(|prefix| {
+            name.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+        });
+        imported.then_some(Kind::Value)
+    }
+
+    /// A reference to `name`, returning what it refers to and where it is.
+    fn reference(&mut self, name: &str) -> (Option<Kind>, (String, usize)) {
+        let subject = self.occurrence(name);
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope.iter_mut().find(|binding| binding.name == name) {
+                binding.used = true;
+                return (Some(binding.kind), subject);
+            }
+        }
+        let kind = self.lookup(name);
+        if kind.is_none() {
+            let message = SatukitanError::UndefinedSymbol(name.to_string()).to_string();
+            self.report(Severity::Error, message, Some(subject.clone()));
+        }
+        (kind, subject)
+    }
+
+    fn visit(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(_) | Expr::Bool(_) | Expr::String(_) => {}
+            Expr::Symbol(name) => {
+                self.reference(name);
+            }
+            Expr::ListLiteral(items) => self.visit_all(items),
+            Expr::List(items) => match items.split_first() {
+                None => {}
+                Some((Expr::Symbol(name), args)) => self.application(name, args),
+                Some(_) => self.visit_all(items),
+            },
+            Expr::Call { func, args } => match func.as_ref() {
+                Expr::Symbol(name) => self.application(name, args),
+                other => {
+                    self.visit(other);
+                    self.visit_all(args);
+                }
+            },
+        }
+    }
+
+    fn visit_all(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.visit(expr);
+        }
+    }
+
+    /// Reports a special form that fails when run. Its arguments are not
+    /// checked, since they mean nothing in a malformed form.
+    fn malformed(&mut self, form: (String, usize), malformed: Malformed, args: &[Expr]) {
+        self.report(
+            Severity::Error,
+            malformed.to_error().to_string(),
+            Some(form),
+        );
+        self.skip(args);
+    }
+
+    /// Counts the identifiers in `exprs` and marks the bindings they name as
+    /// used, without reporting anything about them.
+    fn skip(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            match expr {
+                Expr::Symbol(name) => {
+                    self.occurrence(name);
+                    let binding =
+                        self.scopes.iter_mut().rev().find_map(|scope| {
+                            scope.iter_mut().find(|binding| binding.name == *name)
+                        });
+                    if let Some(binding) = binding {
+                        binding.used = true;
+                    }
+                }
+                Expr::List(items) | Expr::ListLiteral(items) => self.skip(items),
+                Expr::Call { func, args } => {
+                    self.skip(std::slice::from_ref(func));
+                    self.skip(args);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn application(&mut self, name: &str, args: &[Expr]) {
+        match name {
+            "nobu" => {
+                let form = self.occurrence(name);
+                self.nobu(form, args);
+            }
+            "gakas" => {
+                let form = self.occurrence(name);
+                self.gakas(form, args);
+            }
+            "gakasdenu" => {
+                let form = self.occurrence(name);
+                self.gakasdenu(form, args);
+            }
+            "import" => {
+                let form = self.occurrence(name);
+                self.import(form, args);
+            }
+            _ => {
+                let (callee, subject) = self.reference(name);
+                let found = self.argument_count(args);
+                self.visit_all(args);
+                let error = match callee {
+                    Some(Kind::Builtin(arity)) => arity.check(name, found).err(),
+                    Some(Kind::Function(params)) if params != found => {
+                        Some(SatukitanError::arity_exact(name, params, found))
+                    }
+                    _ => None,
+                };
+                if let Some(error) = error {
+                    self.report(Severity::Error, error.to_string(), Some(subject));
+                }
+            }
+        }
+    }
+
+    /// How many values `args` evaluate to once builtins among them have
+    /// taken the arguments following them, as the evaluator does.
+    fn argument_count(&self, args: &[Expr]) -> usize {
+        let mut count = 0;
+        let mut idx = 0;
+        while idx < args.len() {
+            let required = match &args[idx] {
+                Expr::Symbol(name) => match self.lookup(name) {
+                    Some(Kind::Builtin(arity)) => arity.min(),
+                    _ => 0,
+                },
+                _ => 0,
+            };
+            let remaining = args.len() - idx - 1;
+            idx += if required > 0 && remaining >= required {
+                1 + required
+            } else {
+                1
+            };
+            count += 1;
+        }
+        count
+    }
+
+    fn nobu(&mut self, form: (String, usize), args: &[Expr]) {
+        if args.len() != 3 {
+            let malformed = Malformed::Arity {
+                form: "nobu",
+                expected: 3,
+                found: args.len(),
+            };
+            self.report(
+                Severity::Error,
+                malformed.to_error().to_string(),
+                Some(form),
+            );
+        }
+        self.visit_all(args);
+    }
+
+    fn gakas(&mut self, form: (String, usize), args: &[Expr]) {
+        match args {
+            [Expr::Symbol(name), value] => {
+                self.introduce(name);
+                self.visit(value);
+            }
+            [_, _] => {
+                let message = Malformed::Message("gakas: first argument must be symbol");
+                self.malformed(form, message, args);
+            }
+            _ => {
+                let malformed = Malformed::Arity {
+                    form: "gakas",
+                    expected: 2,
+                    found: args.len(),
+                };
+                self.malformed(form, malformed, args);
+            }
+        }
+    }
+
+    fn gakasdenu(&mut self, form: (String, usize), args: &[Expr]) {
+        let [name, params, body] = args else {
+            let malformed = Malformed::Arity {
+                form: "gakasdenu",
+                expected: 3,
+                found: args.len(),
+            };
+            return self.malformed(form, malformed, args);
+        };
+        let Expr::Symbol(name) = name else {
+            let message = Malformed::Message("gakasdenu: function name must be symbol");
+            return self.malformed(form, message, args);
+        };
+        let params = match params {
+            Expr::List(_) => match parameters(params) {
+                Some(params) => params,
+                None => {
+                    let message =
+                        Malformed::Message("gakasdenu: parameter list must contain symbols only");
+                    return self.malformed(form, message, args);
+                }
+            },
+            _ => {
+                let message =
+                    Malformed::Message("gakasdenu: second argument must be parameter list");
+                return self.malformed(form, message, args);
+            }
+        };
+        self.introduce(name);
+
+        let body = extract_body(body);
+        self.push_scope(params.clone(), &body);
+        for param in &params {
+            self.introduce(param);
+        }
+        self.visit_all(&body);
+        // A definition ending the body is the function's result.
+        if let Some(returned) = body.last().and_then(defined_name)
+            && let Some(binding) = self
+                .scopes
+                .last_mut()
+                .and_then(|scope| scope.iter_mut().find(|binding| binding.name == returned))
+        {
+            binding.used = true;
+        }
+        self.pop_scope(false);
+    }
+
+    fn import(&mut self, form: (String, usize), args: &[Expr]) {
+        match args {
+            [Expr::String(_)] => {}
+            [Expr::String(_), Expr::Symbol(alias)] => {
+                self.occurrence(alias);
+            }
+            _ => {
+                let message = Malformed::Message(
+                    "import: expected a path string and an optional prefix symbol",
+                );
+                self.malformed(form, message, args);
+            }
+        }
+    }
+}
+
+/// The name bound by a `gakas` or `gakasdenu` form.
+fn defined_name(expr: &Expr) -> Option<&str> {
+    let (head, args) = match expr {
+        Expr::List(items) => items.split_first()?,
+        Expr::Call { func, args } => (func.as_ref(), args.as_slice()),
+        _ => return None,
+    };
+    match (head.as_symbol()?, args) {
+        ("gakas", [Expr::Symbol(name), _]) | ("gakasdenu", [Expr::Symbol(name), _, _]) => {
+            Some(name)
+        }
+        _ => None,
+    }
+}
+
+fn parameters(expr: &Expr) -> Option<Vec<String>> {
+    match expr {
+        Expr::List(items) => items
+            .iter()
+            .map(|item| item.as_symbol().map(str::to_string))
+            .collect(),
+        _ => None,
+    }
+}
+
+/// The kind of `name` as defined in `body`: a function if every definition
+/// of it there is a `gakasdenu` with the same number of parameters.
+fn definition_kind(name: &str, body: &[Expr]) -> Kind {
+    let mut kinds = Vec::new();
+    definition_kinds(name, body, &mut kinds);
+    match kinds.split_first() {
+        Some((first, rest)) if rest.iter().all(|kind| kind == first) => *first,
+        _ => Kind::Value,
+    }
+}
+
+/// Mirrors [`collect_definitions`], recording the kind of each definition
+/// of `name`.
+fn definition_kinds(name: &str, exprs: &[Expr], kinds: &mut Vec<Kind>) {
+    for expr in exprs {
+        let (head, args) = match expr {
+            Expr::List(items) => match items.split_first() {
+                Some((head, args)) => (head, args),
+                None => continue,
+            },
+            Expr::Call { func, args } => (func.as_ref(), args.as_slice()),
+            Expr::ListLiteral(items) => {
+                definition_kinds(name, items, kinds);
+                continue;
+            }
+            _ => continue,
+        };
+        match (head.as_symbol(), args) {
+            (Some("gakas"), [Expr::Symbol(defined), value]) => {
+                if defined == name {
+                    kinds.push(Kind::Value);
+                }
+                definition_kinds(name, std::slice::from_ref(value), kinds);
+            }
+            (Some("gakasdenu"), [Expr::Symbol(defined), params, _]) => {
+                if defined == name {
+                    kinds.push(parameters(params).map_or(Kind::Value, |p| Kind::Function(p.len())));
+                }
+            }
+            (Some(_), _) => definition_kinds(name, args, kinds),
+            (None, _) => {
+                definition_kinds(name, std::slice::from_ref(head), kinds);
+                definition_kinds(name, args, kinds);
+            }
+        }
+    }
+}
+
+/// Adds the namespace prefix of every well-formed `import` in `exprs`.
+fn collect_imports(exprs: &[Expr], prefixes: &mut Vec<String>) {
+    for expr in exprs {
+        let (head, args) = match expr {
+            Expr::Call { func, args } => (func.as_symbol(), args.as_slice()),
+            Expr::List(items) => match items.split_first() {
+                Some((Expr::Symbol(head), args)) => (Some(head.as_str()), args),
+                _ => (None, items.as_slice()),
+            },
+            Expr::ListLiteral(items) => (None, items.as_slice()),
+            _ => continue,
+        };
+        match (head, args) {
+            (Some("import"), [Expr::String(path)]) => {
+                if let Some(stem) = Path::new(path).file_stem() {
+                    prefixes.push(stem.to_string_lossy().into_owned());
+                }
+            }
+            (Some("import"), [Expr::String(_), Expr::Symbol(alias)]) => {
+                prefixes.push(alias.clone());
+            }
+            _ => collect_imports(args, prefixes),
+        }
+    }
+}
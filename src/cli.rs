@@ -1,11 +1,21 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
+use crate::bench::{Measurement, measure};
+use crate::checker::{Severity, check_program};
+use crate::cst::{Span, SyntaxTree};
+use crate::debugger::{Console, Debugger};
 use crate::error::SatukitanError;
-use crate::interpreter::Interpreter;
+use crate::formatter::format_source;
+use crate::interpreter::{Backend, Interpreter};
+use crate::io::Input;
+use crate::lsp;
+use crate::parser::parse_program;
 use crate::repl;
+use crate::value::Value;
 
 #[derive(Parser)]
 #[command(
@@ -14,39 +24,305 @@ use crate::repl;
     about = "Satukitan scripting language interpreter"
 )]
 pub struct Cli {
+    /// Execution backend
+    #[arg(long, value_enum, global = true, default_value_t = BackendArg::Tree)]
+    backend: BackendArg,
+    /// Fold constant expressions and inline trivial functions before running
+    #[arg(short = 'O', long, global = true)]
+    optimize: bool,
+    /// Disable the filesystem builtins for sandboxed scripts
+    #[arg(long, global = true)]
+    no_fs: bool,
+    /// Start without the standard prelude (max, min, compose)
+    #[arg(long, global = true)]
+    no_prelude: bool,
+    /// Directory searched by `import` after the importing file's directory
+    #[arg(short = 'I', long = "module-path", global = true)]
+    module_paths: Vec<PathBuf>,
     #[command(subcommand)]
     command: Option<Command>,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum BackendArg {
+    /// Tree-walking evaluator
+    Tree,
+    /// Bytecode compiler and stack VM
+    Vm,
+}
+
+impl From<BackendArg> for Backend {
+    fn from(arg: BackendArg) -> Self {
+        match arg {
+            BackendArg::Tree => Backend::TreeWalk,
+            BackendArg::Vm => Backend::Bytecode,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Command {
     /// Run a Satukitan script file (.sample.st)
-    Run { file: PathBuf },
+    Run {
+        file: PathBuf,
+        /// Arguments passed to the script as the `args` list
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
     /// Start an interactive REPL session
     Repl,
+    /// Run a script file under the step debugger
+    Debug {
+        file: PathBuf,
+        /// Pause at LINE; may be repeated. Without breakpoints the debugger
+        /// pauses before the first line
+        #[arg(short = 'b', long = "break", value_name = "LINE")]
+        breakpoints: Vec<usize>,
+        /// Arguments passed to the script as the `args` list
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Measure parse and evaluation time and allocations of a script file
+    Bench {
+        file: PathBuf,
+        /// Number of evaluation runs, each on a fresh interpreter
+        #[arg(short = 'n', long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+    },
+    /// Report likely mistakes in script files without running them
+    Check {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Rewrite script files in the canonical layout
+    Fmt {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Only report files that are not formatted; exit with 1 if any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Start a language server speaking LSP over stdin and stdout
+    Lsp,
+}
+
+impl Cli {
+    fn interpreter(&self) -> Interpreter {
+        let mut interpreter = if self.no_prelude {
+            Interpreter::bare_with_backend(self.backend.into())
+        } else {
+            Interpreter::with_backend(self.backend.into())
+        };
+        interpreter.set_optimize(self.optimize);
+        interpreter.set_fs_access(!self.no_fs);
+        interpreter.set_process_access(true);
+        for dir in &self.module_paths {
+            interpreter.add_module_path(dir);
+        }
+        interpreter
+    }
 }
 
-pub fn run() -> Result<(), SatukitanError> {
+/// Runs the command line and returns the process exit code, which scripts
+/// set with the `exit` builtin.
+pub fn run() -> Result<ExitCode, SatukitanError> {
     let cli = Cli::parse();
-    let mut interpreter = Interpreter::new();
+    let mut interpreter = cli.interpreter();
 
-    match cli.command {
-        Some(Command::Run { file }) => run_file(&mut interpreter, file),
+    match &cli.command {
+        Some(Command::Run { file, args }) => {
+            interpreter.set_args(args.iter().cloned());
+            run_file(&mut interpreter, file.clone())
+        }
+        Some(Command::Debug {
+            file,
+            breakpoints,
+            args,
+        }) => {
+            interpreter.set_args(args.iter().cloned());
+            run_debug(&mut interpreter, file, breakpoints)
+        }
+        Some(Command::Bench { file, iterations }) => {
+            run_bench(&cli, file, *iterations).map(|()| ExitCode::SUCCESS)
+        }
+        Some(Command::Check { files }) => run_check(&interpreter, files),
+        Some(Command::Fmt { files, check }) => run_fmt(files, *check),
+        Some(Command::Lsp) => lsp::serve(std::io::stdin().lock(), std::io::stdout(), &interpreter),
         Some(Command::Repl) | None => repl::start(&mut interpreter),
     }
 }
 
-fn run_file(interpreter: &mut Interpreter, path: PathBuf) -> Result<(), SatukitanError> {
+fn run_file(interpreter: &mut Interpreter, path: PathBuf) -> Result<ExitCode, SatukitanError> {
     validate_extension(path.as_path())?;
-    let source = fs::read_to_string(&path)?;
-    let value = interpreter.eval_str(&source)?;
-    if !value.is_nil() {
-        println!("{}", value);
+    finish(interpreter.eval_file(&path))
+}
+
+/// Prints the result of a script unless it is `nil`, and turns `exit` into
+/// the process exit code.
+fn finish(result: Result<Value, SatukitanError>) -> Result<ExitCode, SatukitanError> {
+    match result {
+        Ok(value) => {
+            if !value.is_nil() {
+                println!("{}", value);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(SatukitanError::Exit(code)) => Ok(ExitCode::from(code)),
+        Err(err) => Err(err),
+    }
+}
+
+fn run_debug(
+    interpreter: &mut Interpreter,
+    path: &Path,
+    breakpoints: &[usize],
+) -> Result<ExitCode, SatukitanError> {
+    validate_extension(path)?;
+    let source = fs::read_to_string(path)?;
+    let console = Console::new(source, Input::stdin(), std::io::stdout());
+    let mut debugger = Debugger::new(console);
+    debugger.set_stop_on_entry(breakpoints.is_empty());
+    for line in breakpoints {
+        debugger.add_breakpoint(*line);
+    }
+    finish(interpreter.debug_file(path, debugger))
+}
+
+/// Prints the diagnostics for each file as `path:line:column: severity:
+/// message` and fails if any of them is an error.
+fn run_check(interpreter: &Interpreter, files: &[PathBuf]) -> Result<ExitCode, SatukitanError> {
+    let mut failed = false;
+    for path in files {
+        validate_extension(path)?;
+        let source = fs::read_to_string(path)?;
+        let checked = SyntaxTree::parse(&source).and_then(|tree| {
+            let program = tree.to_program()?;
+            let diagnostics = check_program(&program, &interpreter.environment().borrow());
+            Ok((tree, diagnostics))
+        });
+        let (tree, diagnostics) = match checked {
+            Ok(checked) => checked,
+            Err(err) => {
+                println!("{}: error: {err}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+        let mut located = diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                let span = diagnostic.span(&tree).unwrap_or(Span { start: 0, end: 0 });
+                (span, diagnostic)
+            })
+            .collect::<Vec<_>>();
+        located.sort_by_key(|(span, _)| span.start);
+        for (span, diagnostic) in located {
+            let (line, column) = span.position(&source);
+            println!(
+                "{}:{}:{}: {}: {}",
+                path.display(),
+                line + 1,
+                column + 1,
+                diagnostic.severity,
+                diagnostic.message
+            );
+            failed |= diagnostic.severity == Severity::Error;
+        }
+    }
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn run_fmt(files: &[PathBuf], check: bool) -> Result<ExitCode, SatukitanError> {
+    let mut unformatted = false;
+    for path in files {
+        validate_extension(path)?;
+        let source = fs::read_to_string(path)?;
+        let formatted = format_source(&source)?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("would reformat {}", path.display());
+            unformatted = true;
+        } else {
+            fs::write(path, formatted)?;
+        }
+    }
+    Ok(if unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn run_bench(cli: &Cli, path: &Path, iterations: u32) -> Result<(), SatukitanError> {
+    validate_extension(path)?;
+    let source = fs::read_to_string(path)?;
+    let (program, parse) = measure(|| parse_program(&source));
+    let program = program?;
+
+    let mut runs = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let mut interpreter = cli.interpreter();
+        interpreter.set_output(std::io::sink());
+        let (result, run) = measure(|| interpreter.eval_program(&program));
+        match result {
+            Ok(_) | Err(SatukitanError::Exit(0)) => {}
+            Err(err) => return Err(err),
+        }
+        runs.push(run);
+    }
+
+    println!("file:        {}", path.display());
+    println!("parse:       {}", format_measurement(&parse));
+    let total = runs
+        .iter()
+        .map(|run| run.elapsed)
+        .sum::<std::time::Duration>();
+    let min = runs.iter().map(|run| run.elapsed).min().unwrap_or_default();
+    let max = runs.iter().map(|run| run.elapsed).max().unwrap_or_default();
+    println!(
+        "evaluate:    mean {:.3} ms, min {:.3} ms, max {:.3} ms over {} runs",
+        millis(total / iterations),
+        millis(min),
+        millis(max),
+        iterations
+    );
+    match runs
+        .iter()
+        .map(|run| run.allocs)
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(allocs) => {
+            let count = allocs.iter().map(|stats| stats.count).sum::<usize>() / allocs.len();
+            let bytes = allocs.iter().map(|stats| stats.bytes).sum::<usize>() / allocs.len();
+            println!("allocations: {count} per run ({bytes} bytes)");
+        }
+        None => println!("allocations: not counted (build with --features alloc-stats)"),
     }
     Ok(())
 }
 
-fn validate_extension(path: &Path) -> Result<(), SatukitanError> {
+fn format_measurement(measurement: &Measurement) -> String {
+    let elapsed = millis(measurement.elapsed);
+    match measurement.allocs {
+        Some(stats) => format!(
+            "{elapsed:.3} ms, {} allocations ({} bytes)",
+            stats.count, stats.bytes
+        ),
+        None => format!("{elapsed:.3} ms"),
+    }
+}
+
+fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub(crate) fn validate_extension(path: &Path) -> Result<(), SatukitanError> {
     let is_valid = path
         .file_name()
         .and_then(|name| name.to_str())
@@ -0,0 +1,186 @@
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::env::Environment;
+use crate::module::Import;
+use crate::resolver::{Lambda, Malformed, Node, resolve_program};
+use crate::symbol::Symbol;
+use crate::value::Value;
+
+/// A single VM instruction. Operands index into the owning [`Chunk`].
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Const(u32),
+    Nil,
+    Load {
+        depth: u32,
+        slot: u32,
+        name: Symbol,
+    },
+    Store(u32),
+    Pop,
+    List(u32),
+    Closure(u32),
+    /// Pops a boolean and jumps to the target when it is false.
+    Branch(u32),
+    Jump(u32),
+    /// Opens an argument list for the callee on top of the stack.
+    BeginArgs,
+    /// Marks the end of one argument; `remaining` arguments follow it. A
+    /// builtin argument may consume the next ones as its own arguments.
+    Arg {
+        remaining: u32,
+    },
+    Call,
+    /// Calls the value on top of the stack with no arguments if it is
+    /// callable, otherwise leaves it in place.
+    Apply,
+    /// Runs the `import` form at the given index and pushes its result.
+    Import(u32),
+    Fail(u32),
+    Return,
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<Value>,
+    pub lambdas: Vec<Rc<Lambda>>,
+    pub imports: Vec<Rc<Import>>,
+    pub failures: Vec<Malformed>,
+}
+
+pub fn compile_program(program: &[Expr], env: &mut Environment) -> Chunk {
+    compile_nodes(&resolve_program(program, env))
+}
+
+pub fn compile_nodes(nodes: &[Node]) -> Chunk {
+    let mut compiler = Compiler::default();
+    compiler.block(nodes);
+    compiler.emit(Op::Return);
+    compiler.chunk
+}
+
+/// Returns the bytecode for a function body, compiling it on first use.
+pub fn lambda_chunk(lambda: &Lambda) -> Rc<Chunk> {
+    lambda
+        .chunk
+        .get_or_init(|| Rc::new(compile_nodes(&lambda.body)))
+        .clone()
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+}
+
+impl Compiler {
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.chunk.code[at] {
+            Op::Branch(to) | Op::Jump(to) => *to = target,
+            other => unreachable!("cannot patch {other:?}"),
+        }
+    }
+
+    fn block(&mut self, nodes: &[Node]) {
+        if nodes.is_empty() {
+            self.emit(Op::Nil);
+            return;
+        }
+        for (idx, node) in nodes.iter().enumerate() {
+            if idx > 0 {
+                self.emit(Op::Pop);
+            }
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Const(value) => {
+                self.chunk.constants.push(value.clone());
+                let idx = self.chunk.constants.len() - 1;
+                self.emit(Op::Const(idx as u32));
+            }
+            Node::Var { depth, slot, name } => {
+                self.emit(Op::Load {
+                    depth: *depth as u32,
+                    slot: *slot as u32,
+                    name: *name,
+                });
+            }
+            Node::ListLiteral(items) => {
+                for item in items {
+                    self.node(item);
+                }
+                self.emit(Op::List(items.len() as u32));
+            }
+            Node::Block(items) => self.block(items),
+            Node::Apply { callee, args } => {
+                self.node(callee);
+                if args.is_empty() {
+                    self.emit(Op::Apply);
+                } else {
+                    self.arguments(args);
+                    self.emit(Op::Call);
+                }
+            }
+            Node::Call { func, args } => {
+                self.node(func);
+                self.arguments(args);
+                self.emit(Op::Call);
+            }
+            Node::Nobu(branches) => {
+                let [condition, then, otherwise] = branches.as_ref();
+                self.node(condition);
+                let branch = self.emit(Op::Branch(0));
+                self.node(then);
+                let jump = self.emit(Op::Jump(0));
+                self.patch(branch);
+                self.node(otherwise);
+                self.patch(jump);
+            }
+            Node::Gakas { slot, value } => {
+                self.node(value);
+                self.emit(Op::Store(*slot as u32));
+            }
+            Node::Gakasdenu { slot, lambda } => {
+                self.chunk.lambdas.push(lambda.clone());
+                let idx = self.chunk.lambdas.len() - 1;
+                self.emit(Op::Closure(idx as u32));
+                self.emit(Op::Store(*slot as u32));
+            }
+            Node::Import(import) => {
+                self.chunk.imports.push(import.clone());
+                let idx = self.chunk.imports.len() - 1;
+                self.emit(Op::Import(idx as u32));
+            }
+            Node::Malformed(malformed) => {
+                self.chunk.failures.push(malformed.clone());
+                let idx = self.chunk.failures.len() - 1;
+                self.emit(Op::Fail(idx as u32));
+            }
+            Node::Located { node, .. } => self.node(node),
+        }
+    }
+
+    fn arguments(&mut self, args: &[Node]) {
+        self.emit(Op::BeginArgs);
+        for (idx, arg) in args.iter().enumerate() {
+            self.node(arg);
+            self.emit(Op::Arg {
+                remaining: (args.len() - idx - 1) as u32,
+            });
+        }
+    }
+}
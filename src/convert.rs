@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::error::SatukitanError;
+use crate::value::{Arity, BuiltinFunction, Value};
+
+/// Conversion from a runtime [`Value`] into a Rust type.
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, SatukitanError>;
+}
+
+/// Conversion from a Rust type into a runtime [`Value`].
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+/// Return types accepted from typed host functions: any [`IntoValue`] type,
+/// or a `Result` of one.
+pub trait IntoResult {
+    fn into_result(self) -> Result<Value, SatukitanError>;
+}
+
+impl<T: IntoValue> IntoResult for T {
+    fn into_result(self) -> Result<Value, SatukitanError> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue> IntoResult for Result<T, SatukitanError> {
+    fn into_result(self) -> Result<Value, SatukitanError> {
+        self.map(IntoValue::into_value)
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        Ok(value)
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        value.as_number()
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        value.as_bool()
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        match value {
+            Value::String(s) => Ok(s.to_string()),
+            other => Err(SatukitanError::type_mismatch("string", other.type_name())),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::from(self)
+    }
+}
+
+/// `nil` on the Satukitan side.
+impl FromValue for () {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        match value {
+            Value::Nil => Ok(()),
+            other => Err(SatukitanError::type_mismatch("nil", other.type_name())),
+        }
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        value.into_list()?.into_iter().map(T::from_value).collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::List(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+/// `None` is `nil`.
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        match value {
+            Value::Nil => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Nil, IntoValue::into_value)
+    }
+}
+
+/// A map is a list of `[key value]` pairs. The order of the pairs produced
+/// by [`IntoValue`] is unspecified.
+impl<K, V> FromValue for HashMap<K, V>
+where
+    K: FromValue + Eq + Hash,
+    V: FromValue,
+{
+    fn from_value(value: Value) -> Result<Self, SatukitanError> {
+        value
+            .into_list()?
+            .into_iter()
+            .map(<(K, V)>::from_value)
+            .collect()
+    }
+}
+
+impl<K: IntoValue, V: IntoValue> IntoValue for HashMap<K, V> {
+    fn into_value(self) -> Value {
+        Value::List(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+fn expect_len(items: &[Value], len: usize) -> Result<(), SatukitanError> {
+    if items.len() == len {
+        Ok(())
+    } else {
+        Err(SatukitanError::type_mismatch(
+            format!("list of length {len}"),
+            format!("list of length {}", items.len()),
+        ))
+    }
+}
+
+/// Argument lists for [`Interpreter::call_typed`](crate::Interpreter::call_typed).
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+/// A Rust function whose parameters and result convert to and from values,
+/// usable as a builtin through [`BuiltinFunction::typed`].
+pub trait HostFunction<Args>: 'static {
+    const ARITY: usize;
+
+    fn invoke(&self, args: &[Value]) -> Result<Value, SatukitanError>;
+}
+
+impl BuiltinFunction {
+    /// Adapts a typed Rust function, e.g. `fn add(a: i64, b: i64) -> i64`.
+    /// Arguments that do not convert fail with a type mismatch.
+    pub fn typed<Args, F: HostFunction<Args>>(name: impl AsRef<str>, func: F) -> Self {
+        BuiltinFunction::new(name, Arity::Exact(F::ARITY), move |args| func.invoke(args))
+    }
+}
+
+macro_rules! tuple_impls {
+    ($len:literal; $($name:ident),*) => {
+        /// A tuple is a list with one item per field.
+        impl<$($name: FromValue),*> FromValue for ($($name,)*) {
+            fn from_value(value: Value) -> Result<Self, SatukitanError> {
+                let items = value.into_list()?;
+                expect_len(&items, $len)?;
+                let mut items = items.into_iter();
+                Ok(($($name::from_value(items.next().expect("length checked"))?,)*))
+            }
+        }
+
+        impl<$($name: IntoValue),*> IntoValue for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn into_value(self) -> Value {
+                let ($($name,)*) = self;
+                Value::from(vec![$($name.into_value()),*])
+            }
+        }
+
+        impl<$($name: IntoValue),*> IntoArgs for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Value> {
+                let ($($name,)*) = self;
+                vec![$($name.into_value()),*]
+            }
+        }
+
+        impl<Func, Ret, $($name),*> HostFunction<($($name,)*)> for Func
+        where
+            Func: Fn($($name),*) -> Ret + 'static,
+            Ret: IntoResult,
+            $($name: FromValue,)*
+        {
+            const ARITY: usize = $len;
+
+            fn invoke(&self, args: &[Value]) -> Result<Value, SatukitanError> {
+                let mut args = args.iter().cloned();
+                self($($name::from_value(args.next().expect("arity checked"))?),*).into_result()
+            }
+        }
+    };
+}
+
+tuple_impls!(1; A);
+tuple_impls!(2; A, B);
+tuple_impls!(3; A, B, C);
+tuple_impls!(4; A, B, C, D);
+tuple_impls!(5; A, B, C, D, E);
+tuple_impls!(6; A, B, C, D, E, F);
+
+impl IntoArgs for Vec<Value> {
+    fn into_args(self) -> Vec<Value> {
+        self
+    }
+}
+
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<Value> {
+        Vec::new()
+    }
+}
+
+impl<Func, Ret> HostFunction<()> for Func
+where
+    Func: Fn() -> Ret + 'static,
+    Ret: IntoResult,
+{
+    const ARITY: usize = 0;
+
+    fn invoke(&self, _args: &[Value]) -> Result<Value, SatukitanError> {
+        self().into_result()
+    }
+}
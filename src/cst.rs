@@ -0,0 +1,431 @@
+use std::cell::Cell;
+use std::fmt;
+
+use crate::ast::{Expr, Program};
+use crate::error::{SatukitanError, unexpected_token};
+use crate::lexer::{Token, TokenKind, is_closed_string, tokenize};
+use crate::value::{bool_token, parse_number_token};
+
+/// Byte range of a node in the source, not counting its trivia.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Zero-based line and column, in characters, of the start of the span
+    /// within `source`.
+    pub fn position(self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start];
+        let line = before.matches('\n').count();
+        let column = before[before.rfind('\n').map_or(0, |idx| idx + 1)..]
+            .chars()
+            .count();
+        (line, column)
+    }
+}
+
+/// Source text that does not affect the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trivia {
+    Whitespace(String),
+    /// A `#` comment, without the line break ending it.
+    Comment(String),
+}
+
+impl Trivia {
+    pub fn text(&self) -> &str {
+        match self {
+            Trivia::Whitespace(text) | Trivia::Comment(text) => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bracket {
+    /// `(...)`, an [`Expr::List`].
+    Paren,
+    /// `[...]`, an [`Expr::ListLiteral`].
+    Square,
+}
+
+impl Bracket {
+    pub fn open(self) -> char {
+        match self {
+            Bracket::Paren => '(',
+            Bracket::Square => '[',
+        }
+    }
+
+    pub fn close(self) -> char {
+        match self {
+            Bracket::Paren => ')',
+            Bracket::Square => ']',
+        }
+    }
+}
+
+/// A syntax node together with the trivia written around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub leading: Vec<Trivia>,
+    pub kind: NodeKind,
+    pub span: Span,
+    /// A comment on the same line after the node, with the spaces before
+    /// it. Empty unless such a comment follows; the line break stays with
+    /// the next node.
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A symbol, numeral or boolean.
+    Identifier(String),
+    /// A string literal as written, with its quotes and escapes.
+    String(String),
+    /// A bracketed group. `closing` is the trivia before the closing
+    /// bracket.
+    Group {
+        bracket: Bracket,
+        children: Vec<Node>,
+        closing: Vec<Trivia>,
+    },
+    /// A top-level call: a name followed by its arguments on the same line.
+    Call { head: Box<Node>, args: Vec<Node> },
+}
+
+impl Node {
+    /// The name of an identifier node.
+    pub fn identifier(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Identifier(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The expression this node stands for. Fails only on invalid escapes
+    /// in string literals.
+    pub fn to_expr(&self) -> Result<Expr, SatukitanError> {
+        Ok(match &self.kind {
+            NodeKind::Identifier(name) => {
+                if let Some(value) = parse_number_token(name) {
+                    Expr::Number(value)
+                } else if let Some(value) = bool_token(name) {
+                    Expr::Bool(value)
+                } else {
+                    Expr::Symbol(name.clone())
+                }
+            }
+            NodeKind::String(raw) => {
+                let content = &raw[1..raw.len() - 1];
+                let string = unescape_string(content).ok_or_else(|| {
+                    SatukitanError::Parse(format!("invalid escape sequence in {raw}"))
+                })?;
+                Expr::String(string)
+            }
+            NodeKind::Group {
+                bracket, children, ..
+            } => {
+                let items = children
+                    .iter()
+                    .map(Node::to_expr)
+                    .collect::<Result<_, _>>()?;
+                match bracket {
+                    Bracket::Paren => Expr::List(items),
+                    Bracket::Square => Expr::ListLiteral(items),
+                }
+            }
+            NodeKind::Call { head, args } => Expr::Call {
+                func: Box::new(head.to_expr()?),
+                args: args.iter().map(Node::to_expr).collect::<Result<_, _>>()?,
+            },
+        })
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading {
+            f.write_str(trivia.text())?;
+        }
+        match &self.kind {
+            NodeKind::Identifier(text) | NodeKind::String(text) => f.write_str(text),
+            NodeKind::Group {
+                bracket,
+                children,
+                closing,
+            } => {
+                write!(f, "{}", bracket.open())?;
+                for child in children {
+                    write!(f, "{child}")?;
+                }
+                for trivia in closing {
+                    f.write_str(trivia.text())?;
+                }
+                write!(f, "{}", bracket.close())
+            }
+            NodeKind::Call { head, args } => {
+                write!(f, "{head}")?;
+                for arg in args {
+                    write!(f, "{arg}")?;
+                }
+                Ok(())
+            }
+        }?;
+        for trivia in &self.trailing {
+            f.write_str(trivia.text())?;
+        }
+        Ok(())
+    }
+}
+
+/// A whole source file, including its comments and whitespace.
+/// Printing the tree with `to_string` gives back the exact source it was
+/// parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree {
+    /// Top-level expressions in source order.
+    pub nodes: Vec<Node>,
+    /// Trivia after the last expression.
+    pub trailing: Vec<Trivia>,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Result<Self, SatukitanError> {
+        Self::parse_located(source).map_err(|(_, err)| err)
+    }
+
+    /// Like [`SyntaxTree::parse`], but a failure also carries the byte
+    /// offset the error points at.
+    pub fn parse_located(source: &str) -> Result<Self, (usize, SatukitanError)> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source),
+            pos: 0,
+            failed_at: Cell::new(0),
+        };
+        parser
+            .program()
+            .map_err(|err| (parser.failed_at.get(), err))
+    }
+
+    /// The program the tree stands for, as evaluated by the interpreter.
+    pub fn to_program(&self) -> Result<Program, SatukitanError> {
+        self.nodes.iter().map(Node::to_expr).collect()
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{node}")?;
+        }
+        for trivia in &self.trailing {
+            f.write_str(trivia.text())?;
+        }
+        Ok(())
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    /// Offset of the last error raised.
+    failed_at: Cell<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn error(&self, token: Option<Token>) -> SatukitanError {
+        let offset = token.map_or(self.source.len(), |token| token.start);
+        self.failed_at.set(offset);
+        unexpected_token(self.source, offset)
+    }
+
+    fn trivia(&mut self) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
+        while let Some(token) = self.peek() {
+            trivia.push(match token.kind {
+                TokenKind::Whitespace => Trivia::Whitespace(token.text.to_string()),
+                TokenKind::Comment => Trivia::Comment(token.text.to_string()),
+                _ => break,
+            });
+            self.pos += 1;
+        }
+        trivia
+    }
+
+    /// A comment after the last node on its line, with the spaces before
+    /// it, or nothing when the line ends without one.
+    fn trailing(&mut self) -> Vec<Trivia> {
+        let spaces = self.peek().is_some_and(|token| {
+            token.kind == TokenKind::Whitespace
+                && token.text.chars().all(|c| matches!(c, ' ' | '\t'))
+        });
+        let comment = self.pos + usize::from(spaces);
+        match self.tokens.get(comment) {
+            Some(token) if token.kind == TokenKind::Comment => self.trivia_until(comment + 1),
+            _ => Vec::new(),
+        }
+    }
+
+    fn trivia_until(&mut self, end: usize) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
+        while self.pos < end {
+            let token = self.tokens[self.pos];
+            trivia.push(match token.kind {
+                TokenKind::Comment => Trivia::Comment(token.text.to_string()),
+                _ => Trivia::Whitespace(token.text.to_string()),
+            });
+            self.pos += 1;
+        }
+        trivia
+    }
+
+    fn program(&mut self) -> Result<SyntaxTree, SatukitanError> {
+        let mut nodes = Vec::new();
+        loop {
+            let leading = self.trivia();
+            if self.peek().is_none() {
+                return Ok(SyntaxTree {
+                    nodes,
+                    trailing: leading,
+                });
+            }
+            let mut node = self.expression(leading)?;
+            node.trailing = self.trailing();
+            nodes.push(node);
+        }
+    }
+
+    /// A top-level expression. A name that is not a numeral or boolean
+    /// takes the arguments following it on the same line.
+    fn expression(&mut self, leading: Vec<Trivia>) -> Result<Node, SatukitanError> {
+        let head = self.argument(leading)?;
+        let takes_args = head
+            .identifier()
+            .is_some_and(|name| parse_number_token(name).is_none() && bool_token(name).is_none());
+        if !takes_args {
+            return Ok(head);
+        }
+
+        let mut args = Vec::new();
+        while let Some(space) = self.peek() {
+            let inline = space.kind == TokenKind::Whitespace
+                && space.text.chars().all(|c| matches!(c, ' ' | '\t'));
+            let followed_by_code = self.tokens.get(self.pos + 1).is_some_and(|next| {
+                !matches!(next.kind, TokenKind::Whitespace | TokenKind::Comment)
+            });
+            if !inline || !followed_by_code {
+                break;
+            }
+            self.pos += 1;
+            args.push(self.argument(vec![Trivia::Whitespace(space.text.to_string())])?);
+        }
+        if args.is_empty() {
+            return Ok(head);
+        }
+        let span = Span {
+            start: head.span.start,
+            end: args.last().map_or(head.span.end, |arg| arg.span.end),
+        };
+        let mut head = head;
+        let leading = std::mem::take(&mut head.leading);
+        Ok(Node {
+            leading,
+            kind: NodeKind::Call {
+                head: Box::new(head),
+                args,
+            },
+            span,
+            trailing: Vec::new(),
+        })
+    }
+
+    /// An identifier, string or group, none of which take arguments.
+    fn argument(&mut self, leading: Vec<Trivia>) -> Result<Node, SatukitanError> {
+        let token = self.peek().ok_or_else(|| self.error(None))?;
+        let kind = match token.kind {
+            TokenKind::Identifier => NodeKind::Identifier(token.text.to_string()),
+            TokenKind::String if is_closed_string(token.text) => {
+                NodeKind::String(token.text.to_string())
+            }
+            TokenKind::Open => return self.group(leading, token),
+            _ => return Err(self.error(Some(token))),
+        };
+        self.pos += 1;
+        Ok(Node {
+            leading,
+            kind,
+            span: Span {
+                start: token.start,
+                end: token.start + token.text.len(),
+            },
+            trailing: Vec::new(),
+        })
+    }
+
+    /// A bracketed group, whose items must be separated by whitespace.
+    fn group(&mut self, leading: Vec<Trivia>, open: Token) -> Result<Node, SatukitanError> {
+        self.pos += 1;
+        let bracket = if open.text == "(" {
+            Bracket::Paren
+        } else {
+            Bracket::Square
+        };
+        let mut children: Vec<Node> = Vec::new();
+        loop {
+            let trivia = self.trivia();
+            let token = self.peek().ok_or_else(|| self.error(None))?;
+            if token.kind == TokenKind::Close {
+                if !token.text.starts_with(bracket.close()) {
+                    return Err(self.error(Some(token)));
+                }
+                self.pos += 1;
+                return Ok(Node {
+                    leading,
+                    kind: NodeKind::Group {
+                        bracket,
+                        children,
+                        closing: trivia,
+                    },
+                    span: Span {
+                        start: open.start,
+                        end: token.start + 1,
+                    },
+                    trailing: Vec::new(),
+                });
+            }
+            if !children.is_empty() && trivia.is_empty() {
+                return Err(self.error(Some(token)));
+            }
+            let mut child = self.argument(trivia)?;
+            child.trailing = self.trailing();
+            children.push(child);
+        }
+    }
+}
+
+fn unescape_string(src: &str) -> Option<String> {
+    let mut result = String::with_capacity(src.len());
+    let mut chars = src.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('"') => result.push('"'),
+                Some('\\') => result.push('\\'),
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                _ => return None,
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    Some(result)
+}
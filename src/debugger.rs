@@ -0,0 +1,385 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::ast::{Expr, Program};
+use crate::cst::{Node, NodeKind, SyntaxTree};
+use crate::env::Environment;
+use crate::error::SatukitanError;
+use crate::io::Input;
+use crate::symbol::Symbol;
+use crate::value::Value;
+
+/// How execution goes on after a pause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Run until the next breakpoint.
+    Continue,
+    /// Pause at the next line, inside any function it calls.
+    StepIn,
+    /// Pause at the next line of the current function, or of its caller
+    /// once it returns.
+    StepOver,
+    /// Pause at the next line after the current function returns.
+    StepOut,
+}
+
+/// Why the program paused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// Before the first line, unless disabled with
+    /// [`Debugger::set_stop_on_entry`].
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+/// A paused program, as shown to a [`DebugFrontend`].
+pub struct Pause<'a> {
+    /// One-based line of the expression about to run.
+    pub line: usize,
+    pub reason: Reason,
+    /// The functions being called, outermost first.
+    pub frames: &'a [Symbol],
+    /// The scope the expression runs in. Its parents form the environment
+    /// chain up to the root.
+    pub env: &'a Rc<RefCell<Environment>>,
+    /// Lines to pause at, which the front-end may change.
+    pub breakpoints: &'a mut BTreeSet<usize>,
+}
+
+impl Pause<'_> {
+    /// The bindings of every scope in the environment chain, innermost
+    /// first and ending with the root.
+    pub fn scopes(&self) -> Vec<Vec<(Symbol, Value)>> {
+        let mut scopes = Vec::new();
+        let mut current = Some(self.env.clone());
+        while let Some(env) = current {
+            let env = env.borrow();
+            scopes.push(env.bindings());
+            current = env.parent();
+        }
+        scopes
+    }
+}
+
+/// Decides what a paused program does next, usually by asking a user.
+pub trait DebugFrontend {
+    /// Called at every pause. An error stops the program with that error.
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Result<Command, SatukitanError>;
+}
+
+impl<F> DebugFrontend for F
+where
+    F: FnMut(&mut Pause<'_>) -> Result<Command, SatukitanError>,
+{
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Result<Command, SatukitanError> {
+        self(pause)
+    }
+}
+
+/// Pauses a program run by [`Interpreter::debug_str`] at breakpoints and
+/// after steps, and hands control to its front-end.
+///
+/// [`Interpreter::debug_str`]: crate::Interpreter::debug_str
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    frames: Vec<Symbol>,
+    resume: Resume,
+    frontend: Box<dyn DebugFrontend>,
+}
+
+/// Where to pause next, besides breakpoints. Depths count the functions
+/// being called when the step was requested.
+#[derive(Clone, Copy, Debug)]
+enum Resume {
+    Entry,
+    Continue,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+impl Debugger {
+    /// A debugger that pauses before the first line.
+    pub fn new(frontend: impl DebugFrontend + 'static) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            frames: Vec::new(),
+            resume: Resume::Entry,
+            frontend: Box::new(frontend),
+        }
+    }
+
+    /// Whether to pause before the first line or run to the first
+    /// breakpoint.
+    pub fn set_stop_on_entry(&mut self, stop: bool) {
+        self.resume = if stop {
+            Resume::Entry
+        } else {
+            Resume::Continue
+        };
+    }
+
+    pub fn add_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    pub(crate) fn enter(&mut self, function: Symbol) {
+        self.frames.push(function);
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Called by the evaluator before the expression at `line` runs in
+    /// `env`.
+    pub(crate) fn before_eval(
+        &mut self,
+        line: usize,
+        env: &Rc<RefCell<Environment>>,
+    ) -> Result<(), SatukitanError> {
+        let depth = self.frames.len();
+        let reason = match self.resume {
+            Resume::Entry => Reason::Entry,
+            _ if self.breakpoints.contains(&line) => Reason::Breakpoint,
+            Resume::StepIn => Reason::Step,
+            Resume::StepOver(from) if depth <= from => Reason::Step,
+            Resume::StepOut(from) if depth < from => Reason::Step,
+            _ => return Ok(()),
+        };
+        let command = self.frontend.paused(&mut Pause {
+            line,
+            reason,
+            frames: &self.frames,
+            env,
+            breakpoints: &mut self.breakpoints,
+        })?;
+        self.resume = match command {
+            Command::Continue => Resume::Continue,
+            Command::StepIn => Resume::StepIn,
+            Command::StepOver => Resume::StepOver(depth),
+            Command::StepOut => Resume::StepOut(depth),
+        };
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("breakpoints", &self.breakpoints)
+            .field("frames", &self.frames)
+            .field("resume", &self.resume)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The one-based line each expression of a program starts on, found from
+/// the syntax tree it was derived from. Expressions are identified by
+/// address, so the table is only valid for the program it was built from.
+pub(crate) struct SourceLines {
+    lines: HashMap<*const Expr, usize>,
+}
+
+impl SourceLines {
+    pub(crate) fn new(source: &str, tree: &SyntaxTree, program: &Program) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect::<Vec<_>>();
+        let mut table = Self {
+            lines: HashMap::new(),
+        };
+        for (node, expr) in tree.nodes.iter().zip(program) {
+            table.record(&starts, node, expr);
+        }
+        table
+    }
+
+    fn record(&mut self, starts: &[usize], node: &Node, expr: &Expr) {
+        let line = starts.partition_point(|start| *start <= node.span.start);
+        self.lines.insert(expr as *const Expr, line);
+        match (&node.kind, expr) {
+            (NodeKind::Group { children, .. }, Expr::List(items) | Expr::ListLiteral(items)) => {
+                for (child, item) in children.iter().zip(items) {
+                    self.record(starts, child, item);
+                }
+            }
+            (NodeKind::Call { head, args }, Expr::Call { func, args: exprs }) => {
+                self.record(starts, head, func);
+                for (arg, item) in args.iter().zip(exprs) {
+                    self.record(starts, arg, item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn line(&self, expr: &Expr) -> Option<usize> {
+        self.lines.get(&(expr as *const Expr)).copied()
+    }
+}
+
+const HELP: &str = "\
+commands:
+  s, step          step into the next line
+  n, next          step over calls to the next line
+  o, out           run until the current function returns
+  c, continue      run until the next breakpoint
+  b, break LINE    pause at LINE
+  d, delete LINE   remove the breakpoint at LINE
+  e, env           show the environment chain
+  p, print NAME    show the value of NAME
+  bt, backtrace    show the functions being called
+  l, list          show the lines around the current one
+  h, help          show this help
+  q, quit          stop the program
+an empty line repeats the last step command";
+
+/// A line-oriented front-end for `satukitan debug`, reading commands from
+/// `input` and writing to `output`. Reading through an [`Input`] lets the
+/// program share standard input with the console.
+pub struct Console<W> {
+    source: String,
+    input: Input,
+    output: W,
+    last: Command,
+}
+
+impl<W: Write> Console<W> {
+    /// `source` is the text of the program, for showing lines.
+    pub fn new(source: impl Into<String>, input: Input, output: W) -> Self {
+        Self {
+            source: source.into(),
+            input,
+            output,
+            last: Command::StepIn,
+        }
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        self.source.lines().nth(line - 1).unwrap_or("")
+    }
+
+    fn prompt(&mut self, pause: &mut Pause<'_>) -> Result<Command, SatukitanError> {
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+            let Some(line) = self.input.read_line()? else {
+                return Err(SatukitanError::Exit(0));
+            };
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let argument = words.next();
+            let step = match command {
+                "" => Some(self.last),
+                "s" | "step" => Some(Command::StepIn),
+                "n" | "next" => Some(Command::StepOver),
+                "o" | "out" => Some(Command::StepOut),
+                "c" | "continue" => Some(Command::Continue),
+                "q" | "quit" => return Err(SatukitanError::Exit(0)),
+                _ => None,
+            };
+            if let Some(step) = step {
+                if step != Command::Continue {
+                    self.last = step;
+                }
+                return Ok(step);
+            }
+            match (command, argument) {
+                ("b" | "break", Some(line)) => match line.parse() {
+                    Ok(line) => {
+                        pause.breakpoints.insert(line);
+                        writeln!(self.output, "breakpoint at line {line}")?;
+                    }
+                    Err(_) => writeln!(self.output, "not a line number: {line}")?,
+                },
+                ("b" | "break", None) => {
+                    let lines = pause
+                        .breakpoints
+                        .iter()
+                        .map(usize::to_string)
+                        .collect::<Vec<_>>();
+                    writeln!(self.output, "breakpoints: {}", lines.join(" "))?;
+                }
+                ("d" | "delete", Some(line)) => {
+                    let removed = line
+                        .parse()
+                        .is_ok_and(|line: usize| pause.breakpoints.remove(&line));
+                    if !removed {
+                        writeln!(self.output, "no breakpoint at line {line}")?;
+                    }
+                }
+                ("e" | "env", None) => self.print_scopes(pause)?,
+                ("p" | "print", Some(name)) => match pause.env.borrow().get(name) {
+                    Some(value) => writeln!(self.output, "{name} = {value}")?,
+                    None => writeln!(
+                        self.output,
+                        "{}",
+                        SatukitanError::UndefinedSymbol(name.into())
+                    )?,
+                },
+                ("bt" | "backtrace", None) => {
+                    for (idx, frame) in pause.frames.iter().rev().enumerate() {
+                        writeln!(self.output, "#{idx} {frame}")?;
+                    }
+                    writeln!(self.output, "#{} <top level>", pause.frames.len())?;
+                }
+                ("l" | "list", None) => {
+                    let first = pause.line.saturating_sub(2).max(1);
+                    let shown = self.source.lines().enumerate().skip(first - 1).take(5);
+                    for (idx, text) in shown {
+                        let marker = if idx + 1 == pause.line { ">" } else { " " };
+                        writeln!(self.output, "{marker}{:>4} {text}", idx + 1)?;
+                    }
+                }
+                ("h" | "help", None) => writeln!(self.output, "{HELP}")?,
+                _ => writeln!(self.output, "unknown command '{}'; try 'help'", line.trim())?,
+            }
+        }
+    }
+
+    /// Prints each scope of the chain. Builtins are left out of the root.
+    fn print_scopes(&mut self, pause: &Pause<'_>) -> Result<(), SatukitanError> {
+        let scopes = pause.scopes();
+        let root = scopes.len() - 1;
+        for (depth, bindings) in scopes.into_iter().enumerate() {
+            let label = if depth == root {
+                "root".to_string()
+            } else {
+                format!("scope {depth}")
+            };
+            let shown = bindings
+                .into_iter()
+                .filter(|(_, value)| depth != root || !matches!(value, Value::Builtin(_)))
+                .map(|(name, value)| match value {
+                    Value::Function(function) => {
+                        format!("{name} = <function ({})>", function.params().join(" "))
+                    }
+                    other => format!("{name} = {other}"),
+                })
+                .collect::<Vec<_>>();
+            writeln!(self.output, "{label}: {}", shown.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> DebugFrontend for Console<W> {
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Result<Command, SatukitanError> {
+        let text = self.source_line(pause.line).trim().to_string();
+        let note = match pause.reason {
+            Reason::Breakpoint => " (breakpoint)",
+            Reason::Entry | Reason::Step => "",
+        };
+        writeln!(self.output, "line {}{note}: {text}", pause.line)?;
+        self.prompt(pause)
+    }
+}
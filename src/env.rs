@@ -1,67 +1,200 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 use crate::error::SatukitanError;
+use crate::module::ModuleLoader;
+use crate::symbol::Symbol;
 use crate::value::{Arity, BuiltinFunction, Value};
 
+/// A scope of bindings stored as slots.
+///
+/// The resolver assigns every binding of a scope a fixed slot index, so the
+/// evaluator reads variables by `(depth, slot)` instead of by name. Slots may
+/// be reserved before they are defined; such slots hold `None`.
 #[derive(Debug)]
 pub struct Environment {
-    values: HashMap<String, Value>,
+    names: Rc<Vec<Symbol>>,
+    slots: Vec<Option<Value>>,
+    index: Option<HashMap<Symbol, usize>>,
     parent: Option<Rc<RefCell<Environment>>>,
+    /// Loader used by `import`, set on root environments only.
+    modules: Option<Weak<ModuleLoader>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
-            values: HashMap::new(),
+            names: Rc::new(Vec::new()),
+            slots: Vec::new(),
+            index: Some(HashMap::new()),
             parent: None,
+            modules: None,
         }
     }
 
     pub fn with_parent(parent: Rc<RefCell<Environment>>) -> Self {
         Self {
-            values: HashMap::new(),
+            names: Rc::new(Vec::new()),
+            slots: Vec::new(),
+            index: None,
             parent: Some(parent),
+            modules: None,
         }
     }
 
-    pub fn define(&mut self, name: impl Into<String>, value: Value) {
-        self.values.insert(name.into(), value);
+    /// Creates a call frame whose slot layout is shared with the function
+    /// that owns it. `slots` holds the arguments followed by locals.
+    pub(crate) fn with_layout(
+        parent: Rc<RefCell<Environment>>,
+        names: Rc<Vec<Symbol>>,
+        slots: Vec<Option<Value>>,
+    ) -> Self {
+        Self {
+            names,
+            slots,
+            index: None,
+            parent: Some(parent),
+            modules: None,
+        }
+    }
+
+    pub fn define(&mut self, name: impl AsRef<str>, value: Value) {
+        self.define_symbol(Symbol::intern(name.as_ref()), value);
+    }
+
+    pub fn define_symbol(&mut self, name: Symbol, value: Value) {
+        let slot = self.reserve(name);
+        self.slots[slot] = Some(value);
     }
 
     pub fn define_builtin(
         &mut self,
-        name: &'static str,
+        name: impl AsRef<str>,
         arity: Arity,
-        func: fn(&[Value]) -> Result<Value, SatukitanError>,
+        func: impl Fn(&[Value]) -> Result<Value, SatukitanError> + 'static,
     ) {
-        self.define(
-            name,
-            Value::Builtin(BuiltinFunction::new(name, arity, func)),
-        );
+        let builtin = BuiltinFunction::new(name, arity, func);
+        self.define(builtin.name, Value::Builtin(builtin));
+    }
+
+    /// Removes the binding of `name` from this scope, if it has one.
+    pub(crate) fn undefine(&mut self, name: Symbol) {
+        if let Some(slot) = self.slot_of(name) {
+            self.slots[slot] = None;
+        }
     }
 
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), SatukitanError> {
-        if self.values.contains_key(name) {
-            self.values.insert(name.to_string(), value);
-            Ok(())
-        } else if let Some(parent) = &self.parent {
-            parent.borrow_mut().assign(name, value)
-        } else {
-            Err(SatukitanError::UndefinedSymbol(name.to_string()))
+        match Symbol::lookup(name) {
+            Some(symbol) => self.assign_symbol(symbol, value),
+            None => Err(SatukitanError::UndefinedSymbol(name.to_string())),
+        }
+    }
+
+    fn assign_symbol(&mut self, name: Symbol, value: Value) -> Result<(), SatukitanError> {
+        match self.slot_of(name) {
+            Some(slot) if self.slots[slot].is_some() => {
+                self.slots[slot] = Some(value);
+                Ok(())
+            }
+            _ => match &self.parent {
+                Some(parent) => parent.borrow_mut().assign_symbol(name, value),
+                None => Err(SatukitanError::UndefinedSymbol(name.to_string())),
+            },
         }
     }
 
     pub fn get(&self, name: &str) -> Option<Value> {
-        if let Some(value) = self.values.get(name) {
+        self.get_symbol(Symbol::lookup(name)?)
+    }
+
+    pub fn get_symbol(&self, name: Symbol) -> Option<Value> {
+        if let Some(value) = self
+            .slot_of(name)
+            .and_then(|slot| self.slots[slot].as_ref())
+        {
             Some(value.clone())
         } else if let Some(parent) = &self.parent {
-            parent.borrow().get(name)
+            parent.borrow().get_symbol(name)
         } else {
             None
         }
     }
+
+    /// Returns the slot for `name` in this scope, allocating an empty one if
+    /// the name has not been seen yet.
+    pub(crate) fn reserve(&mut self, name: Symbol) -> usize {
+        if let Some(slot) = self.slot_of(name) {
+            return slot;
+        }
+        let slot = self.slots.len();
+        Rc::make_mut(&mut self.names).push(name);
+        self.slots.push(None);
+        if let Some(index) = &mut self.index {
+            index.insert(name, slot);
+        }
+        slot
+    }
+
+    pub(crate) fn store(&mut self, slot: usize, value: Value) {
+        self.slots[slot] = Some(value);
+    }
+
+    /// Reads the slot `slot` of the scope `depth` levels up. An undefined slot
+    /// falls back to a by-name lookup in the scopes enclosing its owner, which
+    /// keeps the behaviour of reading a name before a local `gakas` shadows it.
+    pub(crate) fn load(&self, depth: usize, slot: usize, name: Symbol) -> Option<Value> {
+        if depth > 0 {
+            return self.parent.as_ref()?.borrow().load(depth - 1, slot, name);
+        }
+        match self.slots.get(slot) {
+            Some(Some(value)) => Some(value.clone()),
+            _ => self.parent.as_ref()?.borrow().get_symbol(name),
+        }
+    }
+
+    /// The defined bindings of this scope in definition order, without those
+    /// of enclosing scopes.
+    pub fn bindings(&self) -> Vec<(Symbol, Value)> {
+        self.names
+            .iter()
+            .zip(&self.slots)
+            .filter_map(|(name, value)| Some((*name, value.clone()?)))
+            .collect()
+    }
+
+    /// The scope enclosing this one; `None` for a root environment.
+    pub fn parent(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.parent.clone()
+    }
+
+    /// The outermost environment enclosing `env`.
+    pub fn root(env: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        let mut current = env.clone();
+        loop {
+            let parent = current.borrow().parent.clone();
+            match parent {
+                Some(parent) => current = parent,
+                None => return current,
+            }
+        }
+    }
+
+    pub(crate) fn set_modules(&mut self, loader: &Rc<ModuleLoader>) {
+        self.modules = Some(Rc::downgrade(loader));
+    }
+
+    pub(crate) fn modules(&self) -> Option<Rc<ModuleLoader>> {
+        self.modules.as_ref()?.upgrade()
+    }
+
+    fn slot_of(&self, name: Symbol) -> Option<usize> {
+        match &self.index {
+            Some(index) => index.get(&name).copied(),
+            None => self.names.iter().rposition(|candidate| *candidate == name),
+        }
+    }
 }
 
 impl Default for Environment {
@@ -16,6 +16,25 @@ pub enum SatukitanError {
         expected: String,
         found: usize,
     },
+    #[error("step limit of {0} exceeded")]
+    StepLimitExceeded(u64),
+    #[error("time limit of {0:?} exceeded")]
+    TimeLimitExceeded(std::time::Duration),
+    #[error("allocation limit exceeded: {kind} of length {len} is over the limit of {limit}")]
+    AllocationLimitExceeded {
+        kind: &'static str,
+        len: usize,
+        limit: usize,
+    },
+    #[error("recursion limit of {0} nested calls exceeded")]
+    RecursionLimitExceeded(usize),
+    /// Raised by the `exit` builtin to stop the script with a status code.
+    #[error("script exited with code {0}")]
+    Exit(u8),
+    #[error("import error: {0}")]
+    Import(String),
+    #[error("json error: {0}")]
+    Json(String),
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
@@ -45,14 +64,12 @@ impl SatukitanError {
     }
 }
 
-pub fn map_nom_error(input: &str, err: nom::Err<nom::error::Error<&str>>) -> SatukitanError {
-    match err {
-        nom::Err::Incomplete(_) => SatukitanError::Parse("incomplete input".into()),
-        nom::Err::Error(e) | nom::Err::Failure(e) => SatukitanError::Parse(format!(
-            "unexpected token near '{}'",
-            snippet(input, e.input)
-        )),
-    }
+/// A parse error pointing at byte `offset` of `source`.
+pub fn unexpected_token(source: &str, offset: usize) -> SatukitanError {
+    SatukitanError::Parse(format!(
+        "unexpected token near '{}'",
+        snippet(source, &source[offset..])
+    ))
 }
 
 fn snippet(full: &str, tail: &str) -> String {
@@ -2,226 +2,150 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::ast::Expr;
+use crate::debugger::SourceLines;
 use crate::env::Environment;
 use crate::error::SatukitanError;
-use crate::value::{Arity, FunctionValue, Value};
+use crate::limits::Budget;
+use crate::module;
+use crate::resolver::{Node, resolve_located, resolve_program};
+use crate::value::{FunctionValue, Value};
+
+/// Native stack left when a function call moves to a fresh segment.
+const STACK_RED_ZONE: usize = 256 * 1024;
+
+/// Size of each native stack segment allocated for deep recursion.
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
 
 pub fn eval_program(
     program: &[Expr],
     env: Rc<RefCell<Environment>>,
+    budget: &mut Budget,
 ) -> Result<Value, SatukitanError> {
-    let mut last = Value::Nil;
-    for expr in program {
-        last = eval_expr(expr, env.clone())?;
-    }
-    Ok(last)
-}
-
-pub fn eval_expr(expr: &Expr, env: Rc<RefCell<Environment>>) -> Result<Value, SatukitanError> {
-    match expr {
-        Expr::Number(value) => Ok(Value::Number(*value)),
-        Expr::Bool(value) => Ok(Value::Bool(*value)),
-        Expr::String(value) => Ok(Value::String(value.clone())),
-        Expr::Symbol(name) => env
-            .borrow()
-            .get(name)
-            .ok_or_else(|| SatukitanError::UndefinedSymbol(name.clone())),
-        Expr::ListLiteral(items) => {
-            let mut values = Vec::with_capacity(items.len());
-            for item in items {
-                values.push(eval_expr(item, env.clone())?);
-            }
-            Ok(Value::List(values))
-        }
-        Expr::List(items) => eval_list(items, env.clone()),
-        Expr::Call { func, args } => eval_call(func, args, env),
-    }
+    let nodes = resolve_program(program, &mut env.borrow_mut());
+    eval_block(&nodes, &env, budget)
 }
 
-fn eval_list(items: &[Expr], env: Rc<RefCell<Environment>>) -> Result<Value, SatukitanError> {
-    if items.is_empty() {
-        return Ok(Value::List(Vec::new()));
-    }
-
-    if let Expr::Symbol(name) = &items[0] {
-        eval_symbolic_application(name, &items[1..], env)
-    } else {
-        eval_block(items, env)
-    }
+/// Like [`eval_program`], but lets the budget's debugger pause at every
+/// line of `program`, whose lines are given by `lines`.
+pub(crate) fn debug_program(
+    program: &[Expr],
+    lines: &SourceLines,
+    env: Rc<RefCell<Environment>>,
+    budget: &mut Budget,
+) -> Result<Value, SatukitanError> {
+    let nodes = resolve_located(program, lines, &mut env.borrow_mut());
+    eval_block(&nodes, &env, budget)
 }
 
-fn eval_call(
-    func: &Expr,
-    args: &[Expr],
+pub fn eval_expr(
+    expr: &Expr,
     env: Rc<RefCell<Environment>>,
+    budget: &mut Budget,
 ) -> Result<Value, SatukitanError> {
-    if let Expr::Symbol(name) = func {
-        eval_symbolic_application(name, args, env)
-    } else {
-        let callable = eval_expr(func, env.clone())?;
-        let evaluated_args = eval_arguments(args, env)?;
-        apply_callable(callable, evaluated_args)
-    }
+    eval_program(std::slice::from_ref(expr), env, budget)
 }
 
-fn eval_symbolic_application(
-    name: &str,
-    args: &[Expr],
-    env: Rc<RefCell<Environment>>,
+pub fn eval_node(
+    node: &Node,
+    env: &Rc<RefCell<Environment>>,
+    budget: &mut Budget,
 ) -> Result<Value, SatukitanError> {
-    match name {
-        "nobu" => eval_nobu(args, env),
-        "gakas" => eval_gakas(args, env),
-        "gakasdenu" => eval_gakasdenu(args, env),
-        _ => {
-            let callable = env
-                .borrow()
-                .get(name)
-                .ok_or_else(|| SatukitanError::UndefinedSymbol(name.to_string()))?;
+    budget.step()?;
+    match node {
+        Node::Const(value) => {
+            budget.check_value(value)?;
+            Ok(value.clone())
+        }
+        Node::Var { depth, slot, name } => env
+            .borrow()
+            .load(*depth, *slot, *name)
+            .ok_or_else(|| SatukitanError::UndefinedSymbol(name.to_string())),
+        Node::ListLiteral(items) => {
+            let mut values = Vec::with_capacity(items.len());
+            for item in items {
+                values.push(eval_node(item, env, budget)?);
+            }
+            let list = Value::from(values);
+            budget.check_value(&list)?;
+            Ok(list)
+        }
+        Node::Block(items) => eval_block(items, env, budget),
+        Node::Apply { callee, args } => {
+            let callable = eval_node(callee, env, budget)?;
 
             if args.is_empty() {
                 return match callable {
-                    Value::Function(_) | Value::Builtin(_) => apply_callable(callable, Vec::new()),
+                    Value::Function(_) | Value::Builtin(_) => {
+                        apply_callable(callable, Vec::new(), budget)
+                    }
                     other => Ok(other),
                 };
             }
 
-            let evaluated_args = eval_arguments(args, env)?;
-            apply_callable(callable, evaluated_args)
+            let evaluated_args = eval_arguments(args, env, budget)?;
+            apply_callable(callable, evaluated_args, budget)
         }
-    }
-}
-
-fn eval_nobu(args: &[Expr], env: Rc<RefCell<Environment>>) -> Result<Value, SatukitanError> {
-    if args.len() != 3 {
-        return Err(SatukitanError::arity_exact(
-            "nobu".to_string(),
-            3,
-            args.len(),
-        ));
-    }
-
-    let condition = eval_expr(&args[0], env.clone())?;
-    let branch = match condition {
-        Value::Bool(true) => &args[1],
-        Value::Bool(false) => &args[2],
-        other => return Err(SatukitanError::type_mismatch("boolean", other.type_name())),
-    };
-
-    eval_expr(branch, env)
-}
-
-fn eval_gakas(args: &[Expr], env: Rc<RefCell<Environment>>) -> Result<Value, SatukitanError> {
-    if args.len() != 2 {
-        return Err(SatukitanError::arity_exact(
-            "gakas".to_string(),
-            2,
-            args.len(),
-        ));
-    }
-
-    let name = match &args[0] {
-        Expr::Symbol(name) => name.clone(),
-        _ => {
-            return Err(SatukitanError::Eval(
-                "gakas: first argument must be symbol".into(),
-            ));
+        Node::Call { func, args } => {
+            let callable = eval_node(func, env, budget)?;
+            let evaluated_args = eval_arguments(args, env, budget)?;
+            apply_callable(callable, evaluated_args, budget)
         }
-    };
-
-    let value = eval_expr(&args[1], env.clone())?;
-    {
-        let mut env_mut = env.borrow_mut();
-        env_mut.define(name.clone(), value.clone());
-    }
-    Ok(value)
-}
-
-fn eval_gakasdenu(args: &[Expr], env: Rc<RefCell<Environment>>) -> Result<Value, SatukitanError> {
-    if args.len() != 3 {
-        return Err(SatukitanError::arity_exact(
-            "gakasdenu".to_string(),
-            3,
-            args.len(),
-        ));
-    }
-
-    let name = match &args[0] {
-        Expr::Symbol(name) => name.clone(),
-        _ => {
-            return Err(SatukitanError::Eval(
-                "gakasdenu: function name must be symbol".into(),
-            ));
+        Node::Nobu(branches) => {
+            let [condition, then, otherwise] = branches.as_ref();
+            let branch = match eval_node(condition, env, budget)? {
+                Value::Bool(true) => then,
+                Value::Bool(false) => otherwise,
+                other => return Err(SatukitanError::type_mismatch("boolean", other.type_name())),
+            };
+            eval_node(branch, env, budget)
         }
-    };
-
-    let params = extract_params(&args[1])?;
-    let body = extract_body(&args[2]);
-
-    let function = Rc::new(FunctionValue::new(params, body, env.clone()));
-    let value = Value::Function(function.clone());
-
-    {
-        let mut env_mut = env.borrow_mut();
-        env_mut.define(name, value.clone());
-    }
-
-    Ok(value)
-}
-
-fn extract_params(expr: &Expr) -> Result<Vec<String>, SatukitanError> {
-    match expr {
-        Expr::List(items) => {
-            let mut params = Vec::with_capacity(items.len());
-            for item in items {
-                match item {
-                    Expr::Symbol(name) => params.push(name.clone()),
-                    _ => {
-                        return Err(SatukitanError::Eval(
-                            "gakasdenu: parameter list must contain symbols only".into(),
-                        ));
-                    }
-                }
-            }
-            Ok(params)
+        Node::Gakas { slot, value } => {
+            let value = eval_node(value, env, budget)?;
+            env.borrow_mut().store(*slot, value.clone());
+            Ok(value)
         }
-        _ => Err(SatukitanError::Eval(
-            "gakasdenu: second argument must be parameter list".into(),
-        )),
-    }
-}
-
-fn extract_body(expr: &Expr) -> Vec<Expr> {
-    match expr {
-        Expr::List(items) => {
-            if items.iter().all(|item| matches!(item, Expr::List(_))) {
-                items.clone()
-            } else {
-                vec![Expr::List(items.clone())]
+        Node::Gakasdenu { slot, lambda } => {
+            let function = Rc::new(FunctionValue::new(lambda.clone(), env.clone()));
+            let value = Value::Function(function);
+            env.borrow_mut().store(*slot, value.clone());
+            Ok(value)
+        }
+        Node::Import(import) => module::import(import, env, |program, module_env| {
+            eval_program(program, module_env, budget)
+        }),
+        Node::Malformed(malformed) => Err(malformed.to_error()),
+        Node::Located { line, node } => {
+            if let Some(debugger) = budget.debugger() {
+                debugger.before_eval(*line, env)?;
             }
+            eval_node(node, env, budget)
         }
-        other => vec![other.clone()],
     }
 }
 
-fn eval_block(items: &[Expr], env: Rc<RefCell<Environment>>) -> Result<Value, SatukitanError> {
+fn eval_block(
+    items: &[Node],
+    env: &Rc<RefCell<Environment>>,
+    budget: &mut Budget,
+) -> Result<Value, SatukitanError> {
     let mut last = Value::Nil;
-    for expr in items {
-        last = eval_expr(expr, env.clone())?;
+    for node in items {
+        last = eval_node(node, env, budget)?;
     }
     Ok(last)
 }
 
 fn eval_arguments(
-    args: &[Expr],
-    env: Rc<RefCell<Environment>>,
+    args: &[Node],
+    env: &Rc<RefCell<Environment>>,
+    budget: &mut Budget,
 ) -> Result<Vec<Value>, SatukitanError> {
     let mut values = Vec::with_capacity(args.len());
     let mut index = 0;
     while index < args.len() {
-        let value = eval_expr(&args[index], env.clone())?;
+        let value = eval_node(&args[index], env, budget)?;
         if let Some((result, consumed)) =
-            auto_apply_if_callable(&value, &args[index + 1..], env.clone())?
+            auto_apply_if_callable(&value, &args[index + 1..], env, budget)?
         {
             values.push(result);
             index += 1 + consumed;
@@ -235,33 +159,40 @@ fn eval_arguments(
 
 fn auto_apply_if_callable(
     value: &Value,
-    remaining: &[Expr],
-    env: Rc<RefCell<Environment>>,
+    remaining: &[Node],
+    env: &Rc<RefCell<Environment>>,
+    budget: &mut Budget,
 ) -> Result<Option<(Value, usize)>, SatukitanError> {
     if let Value::Builtin(builtin) = value {
-        let required = match builtin.arity() {
-            Arity::Exact(n) => n,
-            Arity::AtLeast(n) => n,
-            Arity::Any => 0,
-        };
+        let required = builtin.arity().min();
         if required == 0 || remaining.len() < required {
             return Ok(None);
         }
         let mut collected = Vec::with_capacity(required);
-        for expr in &remaining[..required] {
-            collected.push(eval_expr(expr, env.clone())?);
+        for node in &remaining[..required] {
+            collected.push(eval_node(node, env, budget)?);
         }
         let result = builtin.call(&collected)?;
+        budget.check_value(&result)?;
         Ok(Some((result, required)))
     } else {
         Ok(None)
     }
 }
 
-fn apply_callable(func: Value, args: Vec<Value>) -> Result<Value, SatukitanError> {
+/// Calls a function or builtin value with already evaluated arguments.
+pub fn apply_callable(
+    func: Value,
+    args: Vec<Value>,
+    budget: &mut Budget,
+) -> Result<Value, SatukitanError> {
     match func {
-        Value::Builtin(builtin) => builtin.call(&args),
-        Value::Function(function) => apply_function(function, args),
+        Value::Builtin(builtin) => {
+            let result = builtin.call(&args)?;
+            budget.check_value(&result)?;
+            Ok(result)
+        }
+        Value::Function(function) => apply_function(function, args, budget),
         other => Err(SatukitanError::Eval(format!(
             "attempted to call non-callable value of type {}",
             other.type_name()
@@ -269,20 +200,24 @@ fn apply_callable(func: Value, args: Vec<Value>) -> Result<Value, SatukitanError
     }
 }
 
-fn apply_function(function: Rc<FunctionValue>, args: Vec<Value>) -> Result<Value, SatukitanError> {
-    if function.params.len() != args.len() {
-        return Err(SatukitanError::arity_exact(
-            "lambda".to_string(),
-            function.params.len(),
-            args.len(),
-        ));
-    }
-
-    let child_env = Rc::new(RefCell::new(Environment::with_parent(function.env.clone())));
-
-    for (param, value) in function.params.iter().zip(args.into_iter()) {
-        child_env.borrow_mut().define(param.clone(), value);
-    }
-
-    eval_block(&function.body, child_env)
+fn apply_function(
+    function: Rc<FunctionValue>,
+    args: Vec<Value>,
+    budget: &mut Budget,
+) -> Result<Value, SatukitanError> {
+    let child_env = function.call_frame(args)?;
+    budget.enter_call()?;
+    if let Some(debugger) = budget.debugger() {
+        debugger.enter(function.lambda.name);
+    }
+    // Script recursion would otherwise be bounded by the native stack of
+    // whichever thread runs the interpreter.
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+        eval_block(&function.lambda.body, &child_env, budget)
+    });
+    if let Some(debugger) = budget.debugger() {
+        debugger.leave();
+    }
+    budget.leave_call();
+    result
 }
@@ -0,0 +1,272 @@
+use crate::cst::{Bracket, Node, NodeKind, SyntaxTree, Trivia};
+use crate::error::SatukitanError;
+
+const INDENT: &str = "    ";
+
+/// Rewrites `source` in the canonical layout used by `satukitan fmt`:
+/// one top-level expression per line, `gakasdenu` bodies on their own
+/// indented lines, and `nobu` branches on their own lines wherever line
+/// breaks are allowed (inside brackets). Comments are kept where they were
+/// written, before or after the code they annotate; runs of blank lines
+/// between top-level expressions collapse to one.
+///
+/// Fails if `source` does not parse. The result always parses to the same
+/// program as `source`, and formatting it again leaves it unchanged.
+pub fn format_source(source: &str) -> Result<String, SatukitanError> {
+    let tree = SyntaxTree::parse(source)?;
+    let program = tree.to_program()?;
+    let formatted = format_tree(&tree);
+    if SyntaxTree::parse(&formatted)?.to_program()? != program {
+        return Err(SatukitanError::Eval(
+            "fmt: formatting would change the meaning of the program".to_string(),
+        ));
+    }
+    Ok(formatted)
+}
+
+/// Prints `tree` in the canonical layout. Top-level expressions written on
+/// one line stay on one line.
+pub fn format_tree(tree: &SyntaxTree) -> String {
+    let mut lines = Lines::default();
+    for node in &tree.nodes {
+        let new_line = leading(&mut lines, &node.leading, 0, true);
+        lines.code(&top_level(node), 0, new_line);
+        trailing(&mut lines, node);
+    }
+    leading(&mut lines, &tree.trailing, 0, true);
+    if lines.lines.last().is_some_and(String::is_empty) {
+        lines.lines.pop();
+    }
+    let mut formatted = lines.lines.join("\n");
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+    formatted
+}
+
+/// Lines being printed. The last line takes more code until a comment ends
+/// it. Lines hold their own indentation and may span several lines of
+/// output when they end in a bracketed block.
+#[derive(Default)]
+struct Lines {
+    lines: Vec<String>,
+    /// Indentation level of the last line.
+    level: usize,
+    open: bool,
+}
+
+impl Lines {
+    /// Lines continuing after the opening bracket of a block, which takes
+    /// comments but no code.
+    fn after(open: char) -> Self {
+        Self {
+            lines: vec![open.to_string()],
+            level: 0,
+            open: false,
+        }
+    }
+
+    /// Whether code goes on a new line, as asked or because the last line
+    /// is closed.
+    fn starts_line(&self, new_line: bool) -> bool {
+        new_line || !self.open
+    }
+
+    /// Adds code to the last line, or starts a line at `level` when
+    /// [`Lines::starts_line`] says so.
+    fn code(&mut self, text: &str, level: usize, new_line: bool) {
+        if self.starts_line(new_line) {
+            self.lines.push(INDENT.repeat(level));
+            self.level = level;
+        } else {
+            self.lines.last_mut().unwrap().push(' ');
+        }
+        self.lines.last_mut().unwrap().push_str(text);
+        self.open = true;
+    }
+
+    /// A comment on a line of its own.
+    fn comment(&mut self, text: &str, level: usize) {
+        self.lines.push(format!("{}{text}", INDENT.repeat(level)));
+        self.open = false;
+    }
+
+    /// A comment ending the last line.
+    fn end_with(&mut self, text: &str) {
+        match self.lines.last_mut() {
+            Some(line) => {
+                line.push(' ');
+                line.push_str(text);
+            }
+            None => self.lines.push(text.to_string()),
+        }
+        self.open = false;
+    }
+
+    fn blank(&mut self) {
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+            self.open = false;
+        }
+    }
+}
+
+/// Prints the comments in the trivia before a node, own-line comments at
+/// `level` and a comment on the same line as the code before it at the end
+/// of that line. Returns whether the node was written on a new line.
+fn leading(lines: &mut Lines, trivia: &[Trivia], level: usize, keep_blank: bool) -> bool {
+    let mut new_line = false;
+    for trivia in trivia {
+        match trivia {
+            Trivia::Whitespace(text) => {
+                let breaks = text.matches('\n').count();
+                new_line |= breaks > 0;
+                if keep_blank && breaks > 1 {
+                    lines.blank();
+                }
+            }
+            Trivia::Comment(text) if new_line => lines.comment(text.trim_end(), level),
+            Trivia::Comment(text) => lines.end_with(text.trim_end()),
+        }
+    }
+    new_line
+}
+
+/// Ends the last line with the comment written after `node`, if any.
+fn trailing(lines: &mut Lines, node: &Node) {
+    for trivia in &node.trailing {
+        if let Trivia::Comment(text) = trivia {
+            lines.end_with(text.trim_end());
+        }
+    }
+}
+
+/// Prints a top-level expression, whose arguments must stay on its line.
+fn top_level(node: &Node) -> String {
+    let NodeKind::Call { head, args } = &node.kind else {
+        return expression(node, 0);
+    };
+    let parts = std::iter::once(&**head).chain(args).collect::<Vec<_>>();
+    parts
+        .iter()
+        .enumerate()
+        .map(|(idx, part)| match definition_body(&parts, idx) {
+            Some(body) => block(body, 0),
+            None => expression(part, 0),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The body of a `gakasdenu` form written as `parts`, when `idx` is its
+/// index. Bodies are always printed as indented blocks.
+fn definition_body<'a>(parts: &[&'a Node], idx: usize) -> Option<&'a Node> {
+    match parts {
+        [keyword, _, params, body]
+            if idx == 3
+                && keyword.identifier() == Some("gakasdenu")
+                && is_paren_group(params)
+                && is_paren_group(body) =>
+        {
+            Some(body)
+        }
+        _ => None,
+    }
+}
+
+fn is_paren_group(node: &Node) -> bool {
+    matches!(
+        node.kind,
+        NodeKind::Group {
+            bracket: Bracket::Paren,
+            ..
+        }
+    )
+}
+
+/// Prints an expression on a line indented to `level`. Groups stay on the
+/// line unless they hold comments or a `gakasdenu` body; later lines of the
+/// result carry their full indentation.
+fn expression(node: &Node, level: usize) -> String {
+    let (bracket, children, closing) = match &node.kind {
+        NodeKind::Identifier(text) | NodeKind::String(text) => return text.clone(),
+        NodeKind::Group {
+            bracket,
+            children,
+            closing,
+        } => (bracket, children, closing),
+        NodeKind::Call { .. } => unreachable!("calls only appear at the top level"),
+    };
+    if has_comments(children, closing) {
+        return block(node, level);
+    }
+    let parts = children.iter().collect::<Vec<_>>();
+    let items = parts
+        .iter()
+        .enumerate()
+        .map(|(idx, child)| match definition_body(&parts, idx) {
+            Some(body) => block(body, level),
+            None => expression(child, level),
+        })
+        .collect::<Vec<_>>();
+    format!("{}{}{}", bracket.open(), items.join(" "), bracket.close())
+}
+
+/// Whether comments are written directly between the brackets of a group.
+fn has_comments(children: &[Node], closing: &[Trivia]) -> bool {
+    children
+        .iter()
+        .flat_map(|child| child.leading.iter().chain(&child.trailing))
+        .chain(closing)
+        .any(|trivia| matches!(trivia, Trivia::Comment(_)))
+}
+
+/// Prints a group with its contents on separate lines, one level deeper
+/// than the line holding the opening bracket. A `nobu` form puts its
+/// condition on the first line and each branch on a line of its own, one
+/// level deeper still; other contents keep the line breaks they were
+/// written with.
+fn block(node: &Node, level: usize) -> String {
+    let NodeKind::Group {
+        bracket,
+        children,
+        closing,
+    } = &node.kind
+    else {
+        return expression(node, level);
+    };
+    if children.is_empty() && !has_comments(children, closing) {
+        return format!("{}{}", bracket.open(), bracket.close());
+    }
+
+    let inner = level + 1;
+    let mut lines = Lines::after(bracket.open());
+    let parts = children.iter().collect::<Vec<_>>();
+    let is_nobu = parts.len() == 4 && parts[0].identifier() == Some("nobu");
+    let is_definition = definition_body(&parts, 3).is_some();
+    for (idx, child) in parts.iter().enumerate() {
+        let (child_level, break_before) = match idx {
+            0 => (inner, true),
+            1 if is_nobu => (inner + 1, false),
+            _ if is_nobu => (inner + 1, true),
+            _ => (inner, false),
+        };
+        let written_on_new_line = leading(&mut lines, &child.leading, child_level, false);
+        let new_line =
+            lines.starts_line(break_before || (written_on_new_line && !is_nobu && !is_definition));
+        let line_level = if new_line { child_level } else { lines.level };
+        let text = match definition_body(&parts, idx) {
+            Some(body) => block(body, line_level),
+            None => expression(child, line_level),
+        };
+        lines.code(&text, line_level, new_line);
+        trailing(&mut lines, child);
+    }
+    leading(&mut lines, closing, inner, false);
+    format!(
+        "{}\n{}{}",
+        lines.lines.join("\n"),
+        INDENT.repeat(level),
+        bracket.close()
+    )
+}
@@ -1,34 +1,361 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use crate::ast::Program;
 use crate::builtins;
+use crate::convert::{FromValue, HostFunction, IntoArgs};
+use crate::cst::SyntaxTree;
+use crate::debugger::{Debugger, SourceLines};
 use crate::env::Environment;
 use crate::error::SatukitanError;
-use crate::evaluator::eval_program;
+use crate::evaluator;
+use crate::io::{FsAccess, Input, Output};
+use crate::limits::{Budget, Limits};
+use crate::module::ModuleLoader;
+use crate::optimizer::optimize_program;
 use crate::parser::parse_program;
-use crate::value::Value;
+use crate::resolver::collect_definitions;
+use crate::value::{Arity, BuiltinFunction, Value};
+use crate::vm;
+
+/// Name of the root binding holding the script's command-line arguments.
+pub const ARGS: &str = "args";
+
+/// Satukitan source of the standard prelude evaluated by
+/// [`Interpreter::new`].
+pub const PRELUDE: &str = include_str!("prelude.st");
+
+/// Execution strategy used by an [`Interpreter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Walk the resolved AST directly.
+    #[default]
+    TreeWalk,
+    /// Compile to bytecode and run it on the stack VM.
+    Bytecode,
+}
 
 pub struct Interpreter {
     env: Rc<RefCell<Environment>>,
+    backend: Backend,
+    optimize: bool,
+    /// Shared with builtins that check sizes before building a value.
+    limits: Rc<Cell<Limits>>,
+    output: Output,
+    input: Input,
+    fs_access: FsAccess,
+    process_access: bool,
+    modules: Rc<ModuleLoader>,
+    args: Value,
+    prelude: bool,
+    host_functions: Vec<BuiltinFunction>,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        let mut root = Environment::new();
-        builtins::install(&mut root);
+        Self::with_backend(Backend::default())
+    }
+
+    pub fn with_backend(backend: Backend) -> Self {
+        let mut interpreter = Self::bare_with_backend(backend);
+        interpreter.load_prelude();
+        interpreter
+    }
+
+    /// An interpreter with the builtins but without the [`PRELUDE`].
+    pub fn bare() -> Self {
+        Self::bare_with_backend(Backend::default())
+    }
+
+    pub fn bare_with_backend(backend: Backend) -> Self {
+        let output = Output::stdout();
+        let input = Input::stdin();
+        let fs_access = FsAccess::default();
+        let args = Value::List(Rc::new([]));
+        let limits = Rc::new(Cell::new(Limits::default()));
+        let mut root = builtin_environment(&output, &input, &fs_access, false, &limits, &args);
+        let modules = Rc::new(ModuleLoader::new(root.bindings(), fs_access.clone()));
+        root.set_modules(&modules);
         Self {
             env: Rc::new(RefCell::new(root)),
+            backend,
+            optimize: false,
+            limits,
+            output,
+            input,
+            fs_access,
+            process_access: false,
+            modules,
+            args,
+            prelude: false,
+            host_functions: Vec::new(),
         }
     }
 
+    fn load_prelude(&mut self) {
+        self.prelude = true;
+        let program = parse_program(PRELUDE).expect("the prelude parses");
+        let mut budget = Budget::new(Limits::default());
+        evaluator::eval_program(&program, self.env.clone(), &mut budget)
+            .expect("the prelude evaluates");
+        let mut names = Vec::new();
+        collect_definitions(&program, &mut names);
+        let env = self.env.borrow();
+        for name in names {
+            if let Some(value) = env.get_symbol(name) {
+                self.modules.define_base(name, value);
+            }
+        }
+    }
+
+    /// Discards every binding made since construction and forgets loaded
+    /// modules. Settings, redirected input and output, the script arguments
+    /// and registered host functions are kept.
+    pub fn reset(&mut self) {
+        let mut root = builtin_environment(
+            &self.output,
+            &self.input,
+            &self.fs_access,
+            self.process_access,
+            &self.limits,
+            &self.args,
+        );
+        self.modules.reset(root.bindings());
+        root.set_modules(&self.modules);
+        self.env = Rc::new(RefCell::new(root));
+        if self.prelude {
+            self.load_prelude();
+        }
+        for builtin in std::mem::take(&mut self.host_functions) {
+            self.register(builtin);
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Enables the constant folding and inlining pass of
+    /// [`optimize_program`] before each program is evaluated.
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits.get()
+    }
+
+    /// Bounds every subsequent evaluation by `limits`. Each call to
+    /// [`eval_str`](Self::eval_str) or [`eval_program`](Self::eval_program)
+    /// gets a fresh budget.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits.set(limits);
+    }
+
+    /// The writer that `sipus` prints to. Standard output by default.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Sends everything scripts print from now on to `writer`, for example an
+    /// [`OutputBuffer`](crate::io::OutputBuffer) to capture it.
+    pub fn set_output(&mut self, writer: impl Write + 'static) {
+        self.output.redirect(writer);
+    }
+
+    /// The source that `read-line`, `read-all` and `lines-iterator` read
+    /// from. Standard input by default.
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    /// Reads all further script input from `reader`, for example a
+    /// `std::io::Cursor` holding test data.
+    pub fn set_input(&mut self, reader: impl BufRead + 'static) {
+        self.input.redirect(reader);
+    }
+
+    /// Binds `args` in the root environment to `args` as a list of strings.
+    pub fn set_args(&mut self, args: impl IntoIterator<Item = String>) {
+        self.args = Value::List(args.into_iter().map(Value::from).collect());
+        self.modules.define_base(ARGS.into(), self.args.clone());
+        self.env.borrow_mut().define(ARGS, self.args.clone());
+    }
+
+    pub fn fs_access(&self) -> bool {
+        self.fs_access.is_enabled()
+    }
+
+    /// Allows or denies the filesystem builtins (`read-file`, `write-file`,
+    /// ...) and `import`. Denied calls fail with a permission error.
+    /// Disabled by default, so embedders running untrusted snippets have to
+    /// opt in; the command-line interpreter enables it unless `--no-fs` is
+    /// given.
+    pub fn set_fs_access(&mut self, enabled: bool) {
+        self.fs_access.set_enabled(enabled);
+    }
+
+    pub fn process_access(&self) -> bool {
+        self.process_access
+    }
+
+    /// Defines or removes the builtins that reach outside the script into the
+    /// host process, `getenv` and `exit`, in the root environment and in
+    /// modules. Disabled by default, so that untrusted snippets can neither
+    /// read secrets from the environment nor stop the embedder; the
+    /// command-line interpreter enables it.
+    pub fn set_process_access(&mut self, enabled: bool) {
+        self.process_access = enabled;
+        let mut env = self.env.borrow_mut();
+        for builtin in builtins::process_builtins() {
+            let name = builtin.name.into();
+            if enabled {
+                self.modules
+                    .define_base(name, Value::Builtin(builtin.clone()));
+                env.define_symbol(name, Value::Builtin(builtin));
+            } else {
+                self.modules.remove_base(name);
+                env.undefine(name);
+            }
+        }
+    }
+
+    /// Adds a directory that `import` searches after the importing file's
+    /// own directory.
+    pub fn add_module_path(&mut self, dir: impl Into<PathBuf>) {
+        self.modules.add_search_path(dir);
+    }
+
+    /// Registers a host builtin in the root environment, replacing any
+    /// previous binding of the same name. Modules loaded afterwards see it
+    /// too.
+    pub fn register(&mut self, builtin: BuiltinFunction) {
+        self.host_functions
+            .retain(|existing| existing.name != builtin.name);
+        self.modules
+            .define_base(builtin.name.into(), Value::Builtin(builtin.clone()));
+        self.env
+            .borrow_mut()
+            .define(builtin.name, Value::Builtin(builtin.clone()));
+        self.host_functions.push(builtin);
+    }
+
+    /// Shorthand for [`register`](Self::register) without documentation.
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        func: impl Fn(&[Value]) -> Result<Value, SatukitanError> + 'static,
+    ) {
+        self.register(BuiltinFunction::new(name, arity, func));
+    }
+
+    /// Registers a typed Rust function as a builtin; see
+    /// [`BuiltinFunction::typed`].
+    pub fn register_typed<Args, F: HostFunction<Args>>(&mut self, name: &str, func: F) {
+        self.register(BuiltinFunction::typed(name, func));
+    }
+
+    /// Builtins registered through [`register`](Self::register), in
+    /// registration order.
+    pub fn host_functions(&self) -> &[BuiltinFunction] {
+        &self.host_functions
+    }
+
+    /// Calls the function or builtin bound to `name` in the root environment
+    /// with `args`, under the interpreter's backend and limits.
+    pub fn call(
+        &mut self,
+        name: &str,
+        args: impl IntoIterator<Item = Value>,
+    ) -> Result<Value, SatukitanError> {
+        let callee = self
+            .env
+            .borrow()
+            .get(name)
+            .ok_or_else(|| SatukitanError::UndefinedSymbol(name.to_string()))?;
+        let args = args.into_iter().collect();
+        let mut budget = Budget::new(self.limits.get());
+        match self.backend {
+            Backend::TreeWalk => evaluator::apply_callable(callee, args, &mut budget),
+            Backend::Bytecode => vm::Vm::new(&mut budget).apply(callee, args),
+        }
+    }
+
+    /// [`call`](Self::call) with Rust arguments and result, e.g.
+    /// `interpreter.call_typed::<i64>("fibo", (10,))`.
+    pub fn call_typed<R: FromValue>(
+        &mut self,
+        name: &str,
+        args: impl IntoArgs,
+    ) -> Result<R, SatukitanError> {
+        R::from_value(self.call(name, args.into_args())?)
+    }
+
     pub fn eval_str(&mut self, source: &str) -> Result<Value, SatukitanError> {
         let program = parse_program(source)?;
-        eval_program(&program, self.env.clone())
+        self.eval_program(&program)
+    }
+
+    /// Evaluates the file at `path`, resolving its imports relative to the
+    /// file's directory.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, SatukitanError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let dir = path.parent().map(Path::to_path_buf);
+        let previous = self.modules.set_entry_dir(dir);
+        let result = self.eval_str(&source);
+        self.modules.set_entry_dir(previous);
+        result
+    }
+
+    /// Evaluates `source` under `debugger`, which pauses before the lines
+    /// it reaches. Debugging always uses the tree-walking evaluator and
+    /// never optimizes.
+    pub fn debug_str(&mut self, source: &str, debugger: Debugger) -> Result<Value, SatukitanError> {
+        let tree = SyntaxTree::parse(source)?;
+        let program = tree.to_program()?;
+        let lines = SourceLines::new(source, &tree, &program);
+        let mut budget = Budget::new(self.limits.get()).with_debugger(debugger);
+        evaluator::debug_program(&program, &lines, self.env.clone(), &mut budget)
+    }
+
+    /// [`debug_str`](Self::debug_str) for the file at `path`, resolving its
+    /// imports as [`eval_file`](Self::eval_file) does.
+    pub fn debug_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        debugger: Debugger,
+    ) -> Result<Value, SatukitanError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let dir = path.parent().map(Path::to_path_buf);
+        let previous = self.modules.set_entry_dir(dir);
+        let result = self.debug_str(&source, debugger);
+        self.modules.set_entry_dir(previous);
+        result
     }
 
     pub fn eval_program(&mut self, program: &Program) -> Result<Value, SatukitanError> {
-        eval_program(program, self.env.clone())
+        if self.optimize {
+            let optimized = optimize_program(program, &self.env.borrow());
+            return self.run(&optimized);
+        }
+        self.run(program)
+    }
+
+    fn run(&mut self, program: &Program) -> Result<Value, SatukitanError> {
+        let mut budget = Budget::new(self.limits.get());
+        match self.backend {
+            Backend::TreeWalk => evaluator::eval_program(program, self.env.clone(), &mut budget),
+            Backend::Bytecode => vm::run_program(program, self.env.clone(), &mut budget),
+        }
     }
 
     pub fn environment(&self) -> Rc<RefCell<Environment>> {
@@ -36,6 +363,30 @@ impl Interpreter {
     }
 }
 
+/// A root environment holding every builtin, wired to the given handles.
+fn builtin_environment(
+    output: &Output,
+    input: &Input,
+    fs_access: &FsAccess,
+    process_access: bool,
+    limits: &Rc<Cell<Limits>>,
+    args: &Value,
+) -> Environment {
+    let mut root = Environment::new();
+    builtins::install(&mut root);
+    builtins::install_lists(&mut root, limits);
+    builtins::install_output(&mut root, output);
+    if process_access {
+        for builtin in builtins::process_builtins() {
+            root.define(builtin.name, Value::Builtin(builtin));
+        }
+    }
+    root.define(ARGS, args.clone());
+    builtins::install_input(&mut root, input);
+    builtins::install_filesystem(&mut root, fs_access);
+    root
+}
+
 impl Default for Interpreter {
     fn default() -> Self {
         Self::new()
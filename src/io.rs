@@ -0,0 +1,188 @@
+use std::cell::{Cell, RefCell};
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+
+/// Destination for text written by scripts, shared between an
+/// [`Interpreter`](crate::Interpreter) and the builtins that print.
+///
+/// Clones refer to the same writer, so replacing it with
+/// [`redirect`](Self::redirect) affects builtins that were installed earlier.
+#[derive(Clone)]
+pub struct Output {
+    writer: Rc<RefCell<Box<dyn Write>>>,
+}
+
+impl Output {
+    pub fn new(writer: impl Write + 'static) -> Self {
+        Self {
+            writer: Rc::new(RefCell::new(Box::new(writer))),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+
+    /// Sends all further output to `writer`.
+    pub fn redirect(&self, writer: impl Write + 'static) {
+        *self.writer.borrow_mut() = Box::new(writer);
+    }
+
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        writeln!(self.writer.borrow_mut(), "{line}")
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.borrow_mut().flush()
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+/// An in-memory writer whose contents stay readable after it has been handed
+/// to an [`Output`].
+#[derive(Clone, Debug, Default)]
+pub struct OutputBuffer {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, decoded lossily as UTF-8.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.borrow()).into_owned()
+    }
+
+    /// Returns the contents and empties the buffer.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.bytes.borrow_mut());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Source of text read by scripts, shared between an
+/// [`Interpreter`](crate::Interpreter) and the builtins that read input.
+#[derive(Clone)]
+pub struct Input {
+    source: Rc<RefCell<Source>>,
+}
+
+enum Source {
+    Stdin,
+    Reader(Box<dyn BufRead>),
+}
+
+impl Input {
+    /// Reads from the process's standard input.
+    pub fn stdin() -> Self {
+        Self {
+            source: Rc::new(RefCell::new(Source::Stdin)),
+        }
+    }
+
+    pub fn new(reader: impl BufRead + 'static) -> Self {
+        Self {
+            source: Rc::new(RefCell::new(Source::Reader(Box::new(reader)))),
+        }
+    }
+
+    /// Reads all further input from `reader`, e.g. an in-memory
+    /// `std::io::Cursor`.
+    pub fn redirect(&self, reader: impl BufRead + 'static) {
+        *self.source.borrow_mut() = Source::Reader(Box::new(reader));
+    }
+
+    /// Reads the next line without its line ending, or `None` at end of input.
+    pub fn read_line(&self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let read = match &mut *self.source.borrow_mut() {
+            Source::Stdin => io::stdin().read_line(&mut line)?,
+            Source::Reader(reader) => reader.read_line(&mut line)?,
+        };
+        if read == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Reads everything up to the end of input, or `None` if nothing is left.
+    pub fn read_all(&self) -> io::Result<Option<String>> {
+        let mut text = String::new();
+        match &mut *self.source.borrow_mut() {
+            Source::Stdin => io::stdin().read_to_string(&mut text)?,
+            Source::Reader(reader) => reader.read_to_string(&mut text)?,
+        };
+        Ok((!text.is_empty()).then_some(text))
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::stdin()
+    }
+}
+
+/// Switch shared between an [`Interpreter`](crate::Interpreter) and its
+/// filesystem builtins. While disabled every filesystem builtin fails with a
+/// permission error. Disabled by default.
+#[derive(Clone, Debug)]
+pub struct FsAccess {
+    enabled: Rc<Cell<bool>>,
+}
+
+impl FsAccess {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Rc::new(Cell::new(enabled)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+
+    pub fn check(&self) -> io::Result<()> {
+        if self.is_enabled() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "filesystem access is disabled",
+            ))
+        }
+    }
+}
+
+impl Default for FsAccess {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
@@ -0,0 +1,432 @@
+use std::fmt::{self, Write};
+
+use crate::error::SatukitanError;
+use crate::value::Value;
+
+/// Deepest array/object nesting accepted by [`parse`].
+const MAX_DEPTH: usize = 256;
+
+/// A JSON document as written, for hosts that need objects rather than the
+/// pair lists [`parse`] produces. Numbers are integers, as in [`parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in document order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Decodes a JSON document, with the same rules and errors as [`parse`].
+    pub fn parse(input: &str) -> Result<Json, SatukitanError> {
+        let mut parser = Parser { input, pos: 0 };
+        parser.skip_whitespace();
+        let json = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos < input.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(json)
+    }
+
+    /// The member `key` of an object; `None` for other documents.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// An object from `(key, value)` pairs.
+    pub fn object<'a>(members: impl IntoIterator<Item = (&'a str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(n: i64) -> Self {
+        Json::Number(n)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n as i64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(items: Vec<Json>) -> Self {
+        Json::Array(items)
+    }
+}
+
+/// Compact JSON, as produced by [`stringify()`].
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        write_json(&mut out, self);
+        f.write_str(&out)
+    }
+}
+
+/// Decodes a JSON document. Arrays become lists, objects become lists of
+/// `[key value]` pairs in document order, `null` becomes `nil`. Numbers must
+/// be integers that fit in an `i64`.
+pub fn parse(input: &str) -> Result<Value, SatukitanError> {
+    Json::parse(input).map(to_value)
+}
+
+fn to_value(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Nil,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => Value::Number(n),
+        Json::String(s) => Value::from(s),
+        Json::Array(items) => Value::List(items.into_iter().map(to_value).collect()),
+        Json::Object(members) => Value::List(
+            members
+                .into_iter()
+                .map(|(key, value)| Value::from(vec![Value::from(key), to_value(value)]))
+                .collect(),
+        ),
+    }
+}
+
+/// Encodes a value as compact JSON. Lists become arrays and `nil` becomes
+/// `null`; functions and builtins cannot be encoded.
+pub fn stringify(value: &Value) -> Result<String, SatukitanError> {
+    let mut out = String::new();
+    write_value(&mut out, value)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut String, value: &Value) -> Result<(), SatukitanError> {
+    match value {
+        Value::Number(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::String(s) => write_string(out, s),
+        Value::List(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_value(out, item)?;
+            }
+            out.push(']');
+        }
+        Value::Nil => out.push_str("null"),
+        Value::Function(_) | Value::Builtin(_) => {
+            return Err(SatukitanError::Json(format!(
+                "cannot encode a {} value",
+                value.type_name()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn write_json(out: &mut String, json: &Json) {
+    match json {
+        Json::Null => out.push_str("null"),
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::Number(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Json::String(s) => write_string(out, s),
+        Json::Array(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_json(out, item);
+            }
+            out.push(']');
+        }
+        Json::Object(members) => {
+            out.push('{');
+            for (idx, (key, value)) in members.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_json(out, value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> SatukitanError {
+        SatukitanError::Json(format!("{message} at byte {}", self.pos))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), SatukitanError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, SatukitanError> {
+        if self.input[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected token"))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, SatukitanError> {
+        match self.peek() {
+            Some(b'{') => self.object(depth + 1),
+            Some(b'[') => self.array(depth + 1),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.keyword("true", Json::Bool(true)),
+            Some(b'f') => self.keyword("false", Json::Bool(false)),
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected token")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    /// Parses the items of an array or object up to `close`.
+    fn items<T>(
+        &mut self,
+        depth: usize,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Result<T, SatukitanError>,
+    ) -> Result<Vec<T>, SatukitanError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        let mut items = Vec::new();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(item(self)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(byte) if byte == close => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close as char))),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, SatukitanError> {
+        self.items(depth, b']', |parser| parser.value(depth))
+            .map(Json::Array)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, SatukitanError> {
+        self.items(depth, b'}', |parser| {
+            if parser.peek() != Some(b'"') {
+                return Err(parser.error("expected string key"));
+            }
+            let key = parser.string()?;
+            parser.skip_whitespace();
+            parser.expect(b':')?;
+            parser.skip_whitespace();
+            let value = parser.value(depth)?;
+            Ok((key, value))
+        })
+        .map(Json::Object)
+    }
+
+    fn number(&mut self) -> Result<Json, SatukitanError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let digits = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let integer = &self.input[digits..self.pos];
+        if integer.is_empty() || (integer.len() > 1 && integer.starts_with('0')) {
+            self.pos = start;
+            return Err(self.error("invalid number"));
+        }
+        if matches!(self.peek(), Some(b'.' | b'e' | b'E')) {
+            self.pos = start;
+            return Err(self.error("only integer numbers are supported"));
+        }
+        self.input[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| {
+                self.pos = start;
+                self.error("number out of range")
+            })
+    }
+
+    fn string(&mut self) -> Result<String, SatukitanError> {
+        self.expect(b'"')?;
+        let mut result = String::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let Some(ch) = rest.chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += ch.len_utf8();
+            match ch {
+                '"' => return Ok(result),
+                '\\' => result.push(self.escape()?),
+                ch if (ch as u32) < 0x20 => {
+                    self.pos -= 1;
+                    return Err(self.error("control character in string"));
+                }
+                ch => result.push(ch),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, SatukitanError> {
+        let escaped = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += 1;
+        Ok(match escaped {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.hex4()?;
+                if (0xd800..0xdc00).contains(&high) {
+                    if !self.input[self.pos..].starts_with("\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                    char::from_u32(code).ok_or_else(|| self.error("invalid escape"))?
+                } else {
+                    char::from_u32(high).ok_or_else(|| self.error("unpaired surrogate"))?
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("invalid escape"));
+            }
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32, SatukitanError> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("checked hex digits"))
+    }
+}
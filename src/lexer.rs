@@ -20,6 +20,103 @@ pub fn identifier(input: &str) -> IResult<&str, &str> {
     .parse(input)
 }
 
+/// Category of a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A symbol, numeral or boolean, as matched by [`identifier`].
+    Identifier,
+    /// A string literal including its quotes. May be unterminated.
+    String,
+    /// A `#` comment up to the end of the line.
+    Comment,
+    /// `(` or `[`.
+    Open,
+    /// `)` or `]`.
+    Close,
+    Whitespace,
+    /// Any character that cannot start another token.
+    Unknown,
+}
+
+/// A slice of the source together with its category and byte offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+}
+
+/// Splits `source` into tokens that cover it completely, so concatenating
+/// their text gives back the input. Never fails; characters the parser
+/// would reject become [`TokenKind::Unknown`].
+pub fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(first) = rest.chars().next() {
+        let at_word_start = source[..source.len() - rest.len()]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        let (len, kind) = if first == '#' && at_word_start && is_comment_start(rest) {
+            (rest.find('\n').unwrap_or(rest.len()), TokenKind::Comment)
+        } else if let Ok((after, _)) = identifier(rest) {
+            (rest.len() - after.len(), TokenKind::Identifier)
+        } else if first == '"' {
+            (string_len(rest), TokenKind::String)
+        } else if first.is_whitespace() {
+            let end = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            (end, TokenKind::Whitespace)
+        } else {
+            let kind = match first {
+                '(' | '[' => TokenKind::Open,
+                ')' | ']' => TokenKind::Close,
+                _ => TokenKind::Unknown,
+            };
+            (first.len_utf8(), kind)
+        };
+        tokens.push(Token {
+            kind,
+            text: &rest[..len],
+            start: source.len() - rest.len(),
+        });
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// Whether a [`TokenKind::String`] token ends with its closing quote.
+pub fn is_closed_string(text: &str) -> bool {
+    closed_string_len(text) == Some(text.len())
+}
+
+/// Whether the `#` at the start of `input` opens a comment: like the parser,
+/// only a `#` standing alone as a word does.
+fn is_comment_start(input: &str) -> bool {
+    input[1..].chars().next().is_none_or(char::is_whitespace)
+}
+
+/// Length of the string literal at the start of `input`, up to and including
+/// the closing quote or to the end of the input if there is none.
+fn string_len(input: &str) -> usize {
+    closed_string_len(input).unwrap_or(input.len())
+}
+
+/// Length of the string literal at the start of `input` if it is closed.
+fn closed_string_len(input: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, ch) in input.char_indices().skip(1) {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(idx + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
 fn is_inline_space(c: char) -> bool {
     matches!(c, ' ' | '\t')
 }
@@ -29,5 +126,5 @@ fn is_identifier_start(c: char) -> bool {
 }
 
 fn is_identifier_continue(c: char) -> bool {
-    c.is_ascii_alphanumeric() || matches!(c, '_' | '-')
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
 }
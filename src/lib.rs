@@ -1,15 +1,33 @@
 pub mod ast;
+pub mod bench;
 pub mod builtins;
+pub mod checker;
 pub mod cli;
+pub mod compiler;
+pub mod convert;
+pub mod cst;
+pub mod debugger;
 pub mod env;
 pub mod error;
 pub mod evaluator;
+pub mod formatter;
 pub mod interpreter;
+pub mod io;
+pub mod json;
 pub mod lexer;
+pub mod limits;
+pub mod lsp;
+pub mod module;
+pub mod optimizer;
 pub mod parser;
 pub mod repl;
+pub mod resolver;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod symbol;
 pub mod value;
+pub mod vm;
 
 pub use error::SatukitanError;
-pub use interpreter::Interpreter;
+pub use interpreter::{Backend, Interpreter};
 pub use value::Value;
@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+use crate::debugger::Debugger;
+use crate::error::SatukitanError;
+use crate::value::Value;
+
+/// How often the wall clock is consulted, in steps.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Call depth allowed when some other limit is set but
+/// [`Limits::max_call_depth`] is not.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+/// Resource limits applied to each evaluation of an
+/// [`Interpreter`](crate::Interpreter). `None` leaves a resource unbounded,
+/// except that setting any limit also bounds the call depth.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Evaluation steps: expressions for the tree walker, instructions for
+    /// the bytecode VM.
+    pub max_steps: Option<u64>,
+    /// Wall-clock time for one evaluation.
+    pub timeout: Option<Duration>,
+    /// Length of any list built while evaluating.
+    pub max_list_len: Option<usize>,
+    /// Length in bytes of any string built while evaluating.
+    pub max_string_len: Option<usize>,
+    /// Nesting of function calls. Defaults to [`DEFAULT_MAX_CALL_DEPTH`]
+    /// when any other limit is set, so that a step or time budget also stops
+    /// runaway recursion before it exhausts memory.
+    pub max_call_depth: Option<usize>,
+}
+
+impl Limits {
+    /// The call depth limit in effect for these limits.
+    pub fn call_depth(&self) -> Option<usize> {
+        let limited = self.max_steps.is_some()
+            || self.timeout.is_some()
+            || self.max_list_len.is_some()
+            || self.max_string_len.is_some();
+        self.max_call_depth
+            .or(limited.then_some(DEFAULT_MAX_CALL_DEPTH))
+    }
+}
+
+/// Tracks resource usage of one evaluation against its [`Limits`].
+#[derive(Debug)]
+pub struct Budget {
+    limits: Limits,
+    steps: u64,
+    /// Step count at which the step and time limits are checked next, so
+    /// that most steps only compare one counter.
+    next_check: u64,
+    started: Instant,
+    depth: usize,
+    /// Pauses the tree walker at [`Node::Located`](crate::resolver::Node)
+    /// expressions, when debugging.
+    debugger: Option<Box<Debugger>>,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        let limits = Limits {
+            max_call_depth: limits.call_depth(),
+            ..limits
+        };
+        let mut budget = Self {
+            limits,
+            steps: 0,
+            next_check: 0,
+            started: Instant::now(),
+            depth: 0,
+            debugger: None,
+        };
+        budget.schedule_check();
+        budget
+    }
+
+    pub(crate) fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(Box::new(debugger));
+        self
+    }
+
+    pub(crate) fn debugger(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_deref_mut()
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(Limits::default())
+    }
+
+    #[inline]
+    pub fn step(&mut self) -> Result<(), SatukitanError> {
+        self.steps += 1;
+        if self.steps < self.next_check {
+            return Ok(());
+        }
+        self.check_limits()
+    }
+
+    #[cold]
+    fn check_limits(&mut self) -> Result<(), SatukitanError> {
+        if let Some(max) = self.limits.max_steps
+            && self.steps > max
+        {
+            return Err(SatukitanError::StepLimitExceeded(max));
+        }
+        if let Some(timeout) = self.limits.timeout
+            && self.started.elapsed() > timeout
+        {
+            return Err(SatukitanError::TimeLimitExceeded(timeout));
+        }
+        self.schedule_check();
+        Ok(())
+    }
+
+    /// Sets the next step count at which [`Budget::step`] checks the step
+    /// limit or the clock.
+    fn schedule_check(&mut self) {
+        let clock = match self.limits.timeout {
+            Some(_) => self.steps + CLOCK_INTERVAL,
+            None => u64::MAX,
+        };
+        let steps = self
+            .limits
+            .max_steps
+            .map_or(u64::MAX, |max| max.saturating_add(1));
+        self.next_check = clock.min(steps);
+    }
+
+    pub fn enter_call(&mut self) -> Result<(), SatukitanError> {
+        self.depth += 1;
+        match self.limits.max_call_depth {
+            Some(max) if self.depth > max => Err(SatukitanError::RecursionLimitExceeded(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn leave_call(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Rejects a freshly built list or string that is larger than allowed.
+    pub fn check_value(&self, value: &Value) -> Result<(), SatukitanError> {
+        let (kind, len, limit) = match value {
+            Value::List(items) => ("list", items.len(), self.limits.max_list_len),
+            Value::String(text) => ("string", text.len(), self.limits.max_string_len),
+            _ => return Ok(()),
+        };
+        match limit {
+            Some(limit) if len > limit => {
+                Err(SatukitanError::AllocationLimitExceeded { kind, len, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+use crate::checker::{Severity, check_program};
+use crate::cst::{Bracket, Node, NodeKind, Span, SyntaxTree};
+use crate::error::SatukitanError;
+use crate::formatter::format_source;
+use crate::interpreter::Interpreter;
+use crate::json::Json;
+use crate::repl::SatukitanHelper;
+use crate::resolver::SPECIAL_FORMS;
+use crate::value::{Value, bool_token, parse_number_token};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// Largest message body accepted, so a bad `Content-Length` cannot make the
+/// server allocate without bound.
+const MAX_MESSAGE_LENGTH: usize = 64 * 1024 * 1024;
+
+/// `TextDocumentSyncKind.Full`: clients send the whole text on each change.
+const FULL_SYNC: i64 = 1;
+
+const KIND_FUNCTION: i64 = 3;
+const KIND_VARIABLE: i64 = 6;
+const KIND_KEYWORD: i64 = 14;
+const KIND_CONSTANT: i64 = 21;
+
+type Response = Result<Json, (i64, String)>;
+
+/// Runs a Language Server Protocol server reading messages from `input` and
+/// writing to `output` until the client sends `exit` or closes `input`.
+///
+/// Open documents get diagnostics from the parser and
+/// [`check_program`], checked against the root environment of
+/// `interpreter`. The server also answers completion, hover, go-to-definition
+/// and whole-document formatting requests. Succeeds only if the client shut
+/// the server down before exiting.
+pub fn serve(
+    mut input: impl BufRead,
+    output: impl Write,
+    interpreter: &Interpreter,
+) -> Result<ExitCode, SatukitanError> {
+    let mut server = Server {
+        output,
+        interpreter,
+        documents: HashMap::new(),
+        initialized: false,
+        shut_down: false,
+    };
+    while let Some(body) = read_message(&mut input)? {
+        let message = match Json::parse(&body) {
+            Ok(message) => message,
+            Err(err) => {
+                server.respond(Json::Null, Err((PARSE_ERROR, err.to_string())))?;
+                continue;
+            }
+        };
+        if message.get("method").and_then(Json::as_str) == Some("exit") {
+            break;
+        }
+        server.handle(&message)?;
+    }
+    Ok(if server.shut_down {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Reads one message framed by a `Content-Length` header. `None` once the
+/// input ends; an error for bodies over [`MAX_MESSAGE_LENGTH`].
+fn read_message(input: &mut impl BufRead) -> Result<Option<String>, SatukitanError> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = Some(value.trim().parse::<usize>().map_err(|_| {
+                SatukitanError::Eval(format!("lsp: invalid Content-Length '{}'", value.trim()))
+            })?);
+        }
+    }
+    let length = length.ok_or_else(|| {
+        SatukitanError::Eval("lsp: message without Content-Length header".to_string())
+    })?;
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(SatukitanError::Eval(format!(
+            "lsp: message of {length} bytes exceeds the limit of {MAX_MESSAGE_LENGTH} bytes"
+        )));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| SatukitanError::Eval("lsp: message is not valid UTF-8".to_string()))
+}
+
+struct Server<'a, W> {
+    output: W,
+    interpreter: &'a Interpreter,
+    /// Text of the open documents by URI.
+    documents: HashMap<String, String>,
+    initialized: bool,
+    shut_down: bool,
+}
+
+impl<W: Write> Server<'_, W> {
+    fn send(&mut self, message: Json) -> Result<(), SatukitanError> {
+        let body = message.to_string();
+        write!(self.output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn respond(&mut self, id: Json, response: Response) -> Result<(), SatukitanError> {
+        let outcome = match response {
+            Ok(result) => ("result", result),
+            Err((code, message)) => (
+                "error",
+                Json::object([("code", code.into()), ("message", message.into())]),
+            ),
+        };
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id),
+            outcome,
+        ]))
+    }
+
+    fn handle(&mut self, message: &Json) -> Result<(), SatukitanError> {
+        // Responses to requests we never send are ignored.
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            return Ok(());
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+        let response = if !self.initialized && method != "initialize" {
+            Err((SERVER_NOT_INITIALIZED, "server not initialized".to_string()))
+        } else if self.shut_down {
+            Err((INVALID_REQUEST, "server is shutting down".to_string()))
+        } else {
+            self.request(method, params)
+        };
+        self.respond(id.clone(), response)
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Response {
+        match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(Json::object([
+                    (
+                        "capabilities",
+                        Json::object([
+                            ("textDocumentSync", FULL_SYNC.into()),
+                            ("completionProvider", Json::object([])),
+                            ("hoverProvider", true.into()),
+                            ("definitionProvider", true.into()),
+                            ("documentFormattingProvider", true.into()),
+                        ]),
+                    ),
+                    (
+                        "serverInfo",
+                        Json::object([
+                            ("name", "satukitan".into()),
+                            ("version", env!("CARGO_PKG_VERSION").into()),
+                        ]),
+                    ),
+                ]))
+            }
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/completion" => {
+                let (_, source) = self.document(params)?;
+                Ok(self.completion(source))
+            }
+            "textDocument/hover" => {
+                let (_, source) = self.document(params)?;
+                Ok(self.hover(source, params.get("position")))
+            }
+            "textDocument/definition" => {
+                let (uri, source) = self.document(params)?;
+                Ok(definition(uri, source, params.get("position")))
+            }
+            "textDocument/formatting" => {
+                let (_, source) = self.document(params)?;
+                Ok(formatting(source))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method: {method}"))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Result<(), SatukitanError> {
+        let Some(uri) = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+        else {
+            return Ok(());
+        };
+        let text = match method {
+            "textDocument/didOpen" => params
+                .get("textDocument")
+                .and_then(|document| document.get("text")),
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish(uri, Vec::new());
+            }
+            _ => return Ok(()),
+        };
+        let Some(text) = text.and_then(Json::as_str) else {
+            return Ok(());
+        };
+        let diagnostics = self.diagnostics(text);
+        self.documents.insert(uri.to_string(), text.to_string());
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Json>) -> Result<(), SatukitanError> {
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+            ),
+        ]))
+    }
+
+    /// The URI and text of the open document a request is about.
+    fn document<'p>(&self, params: &'p Json) -> Result<(&'p str, &str), (i64, String)> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let source = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document is not open: {uri}")))?;
+        Ok((uri, source))
+    }
+
+    /// The parse error of `source`, or else what [`check_program`] reports.
+    fn diagnostics(&self, source: &str) -> Vec<Json> {
+        let tree = match SyntaxTree::parse_located(source) {
+            Ok(tree) => tree,
+            Err((offset, err)) => {
+                let span = Span {
+                    start: offset,
+                    end: offset,
+                };
+                return vec![diagnostic(source, span, Severity::Error, &err.to_string())];
+            }
+        };
+        let program = match tree.to_program() {
+            Ok(program) => program,
+            Err(err) => {
+                let span = tree
+                    .nodes
+                    .iter()
+                    .find(|node| node.to_expr().is_err())
+                    .map_or(Span { start: 0, end: 0 }, |node| node.span);
+                return vec![diagnostic(source, span, Severity::Error, &err.to_string())];
+            }
+        };
+        check_program(&program, &self.interpreter.environment().borrow())
+            .into_iter()
+            .map(|found| {
+                let span = found.span(&tree).unwrap_or(Span { start: 0, end: 0 });
+                diagnostic(source, span, found.severity, &found.message)
+            })
+            .collect()
+    }
+
+    /// The names defined in `source` followed by the REPL's keywords and the
+    /// root bindings of the interpreter.
+    fn completion(&self, source: &str) -> Json {
+        let mut items = Vec::new();
+        let mut seen = Vec::new();
+        let tree = SyntaxTree::parse(source).ok();
+        for found in tree.iter().flat_map(|tree| definitions(&tree.nodes)) {
+            if seen.contains(&found.name) {
+                continue;
+            }
+            seen.push(found.name);
+            let (kind, detail) = match &found.params {
+                Some(params) => (KIND_FUNCTION, Some(format!("({})", params.join(" ")))),
+                None => (KIND_VARIABLE, None),
+            };
+            items.push(completion_item(found.name, kind, detail, None));
+        }
+        let globals = self.interpreter.environment();
+        let globals = globals.borrow();
+        for info in SatukitanHelper::new(self.interpreter).entries() {
+            if seen.contains(&info.name) {
+                continue;
+            }
+            let kind = if SPECIAL_FORMS.contains(&info.name) {
+                KIND_KEYWORD
+            } else if parse_number_token(info.name).is_some() || bool_token(info.name).is_some() {
+                KIND_CONSTANT
+            } else {
+                match globals.get(info.name) {
+                    Some(Value::Function(_) | Value::Builtin(_)) | None => KIND_FUNCTION,
+                    Some(_) => KIND_VARIABLE,
+                }
+            };
+            let detail = info.annotation.map(|annotation| annotation.into_owned());
+            let doc = info.doc.map(|doc| doc.into_owned());
+            items.push(completion_item(info.name, kind, detail, doc));
+        }
+        Json::Array(items)
+    }
+
+    /// The value of a numeral or boolean, the parameters of a function
+    /// defined in the document, or the signature of a keyword or builtin.
+    fn hover(&self, source: &str, position: Option<&Json>) -> Json {
+        let Some((tree, offset)) = parse_at(source, position) else {
+            return Json::Null;
+        };
+        let Some((name, span)) = identifier_at(&tree.nodes, offset) else {
+            return Json::Null;
+        };
+        let text = if let Some(value) = parse_number_token(name) {
+            format!("{name} : number = {value}")
+        } else if let Some(value) = bool_token(name) {
+            format!("{name} : boolean = {value}")
+        } else if let Some(params) = definitions(&tree.nodes)
+            .into_iter()
+            .find(|found| found.name == name)
+            .and_then(|found| found.params)
+        {
+            format!("{name} : function ({})", params.join(" "))
+        } else if let Some(info) = SatukitanHelper::new(self.interpreter)
+            .entries()
+            .into_iter()
+            .find(|info| info.name == name)
+        {
+            let mut text = match &info.annotation {
+                Some(annotation) => format!("{name} {annotation}"),
+                None => name.to_string(),
+            };
+            if let Some(doc) = &info.doc {
+                text.push_str("\n\n");
+                text.push_str(doc);
+            }
+            text
+        } else {
+            return Json::Null;
+        };
+        Json::object([
+            (
+                "contents",
+                Json::object([("kind", "plaintext".into()), ("value", text.into())]),
+            ),
+            ("range", range(source, span)),
+        ])
+    }
+}
+
+/// Where the `gakasdenu` or `gakas` defining the name under the cursor
+/// names it.
+fn definition(uri: &str, source: &str, position: Option<&Json>) -> Json {
+    let Some((tree, offset)) = parse_at(source, position) else {
+        return Json::Null;
+    };
+    let Some((name, _)) = identifier_at(&tree.nodes, offset) else {
+        return Json::Null;
+    };
+    Json::Array(
+        definitions(&tree.nodes)
+            .into_iter()
+            .filter(|found| found.name == name)
+            .map(|found| Json::object([("uri", uri.into()), ("range", range(source, found.span))]))
+            .collect(),
+    )
+}
+
+/// A single edit replacing the whole document with its formatted text; no
+/// edits if it is already formatted or does not parse.
+fn formatting(source: &str) -> Json {
+    let edits = match format_source(source) {
+        Ok(formatted) if formatted != source => vec![Json::object([
+            (
+                "range",
+                range(
+                    source,
+                    Span {
+                        start: 0,
+                        end: source.len(),
+                    },
+                ),
+            ),
+            ("newText", formatted.into()),
+        ])],
+        _ => Vec::new(),
+    };
+    Json::Array(edits)
+}
+
+fn diagnostic(source: &str, span: Span, severity: Severity, message: &str) -> Json {
+    let severity: i64 = match severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    Json::object([
+        ("range", range(source, span)),
+        ("severity", severity.into()),
+        ("source", "satukitan".into()),
+        ("message", message.into()),
+    ])
+}
+
+fn completion_item(name: &str, kind: i64, detail: Option<String>, doc: Option<String>) -> Json {
+    let mut members = vec![("label", name.into()), ("kind", kind.into())];
+    if let Some(detail) = detail {
+        members.push(("detail", detail.into()));
+    }
+    if let Some(doc) = doc {
+        members.push(("documentation", doc.into()));
+    }
+    Json::object(members)
+}
+
+/// A name bound by `gakasdenu` (with its parameters) or `gakas`.
+struct Definition<'a> {
+    name: &'a str,
+    span: Span,
+    params: Option<Vec<&'a str>>,
+}
+
+/// Every definition in `nodes`, including those in function bodies, in
+/// source order.
+fn definitions(nodes: &[Node]) -> Vec<Definition<'_>> {
+    let mut found = Vec::new();
+    collect_definitions(nodes.iter(), &mut found);
+    found
+}
+
+fn collect_definitions<'a>(nodes: impl Iterator<Item = &'a Node>, found: &mut Vec<Definition<'a>>) {
+    for node in nodes {
+        let items: Vec<&Node> = match &node.kind {
+            NodeKind::Call { head, args } => std::iter::once(&**head).chain(args).collect(),
+            NodeKind::Group { children, .. } => children.iter().collect(),
+            NodeKind::Identifier(_) | NodeKind::String(_) => continue,
+        };
+        let is_application = !matches!(
+            node.kind,
+            NodeKind::Group {
+                bracket: Bracket::Square,
+                ..
+            }
+        );
+        if is_application
+            && let [keyword, name, rest @ ..] = items.as_slice()
+            && let Some(keyword @ ("gakas" | "gakasdenu")) = keyword.identifier()
+            && let Some(text) = name.identifier()
+        {
+            let params = (keyword == "gakasdenu").then(|| match rest.first().map(|it| &it.kind) {
+                Some(NodeKind::Group { children, .. }) => {
+                    children.iter().filter_map(Node::identifier).collect()
+                }
+                _ => Vec::new(),
+            });
+            found.push(Definition {
+                name: text,
+                span: name.span,
+                params,
+            });
+        }
+        collect_definitions(items.into_iter(), found);
+    }
+}
+
+/// The tree of `source` and the byte offset of an LSP position in it.
+fn parse_at(source: &str, position: Option<&Json>) -> Option<(SyntaxTree, usize)> {
+    let offset = offset(source, position?)?;
+    Some((SyntaxTree::parse(source).ok()?, offset))
+}
+
+/// The identifier touching byte `offset`, including at its end.
+fn identifier_at(nodes: &[Node], offset: usize) -> Option<(&str, Span)> {
+    nodes
+        .iter()
+        .filter(|node| node.span.start <= offset && offset <= node.span.end)
+        .find_map(|node| match &node.kind {
+            NodeKind::Identifier(name) => Some((name.as_str(), node.span)),
+            NodeKind::String(_) => None,
+            NodeKind::Group { children, .. } => identifier_at(children, offset),
+            NodeKind::Call { head, args } => identifier_at(std::slice::from_ref(&**head), offset)
+                .or_else(|| identifier_at(args, offset)),
+        })
+}
+
+fn range(source: &str, span: Span) -> Json {
+    Json::object([
+        ("start", position(source, span.start)),
+        ("end", position(source, span.end)),
+    ])
+}
+
+/// The LSP position of byte `offset`: a zero-based line and a column in
+/// UTF-16 code units.
+fn position(source: &str, offset: usize) -> Json {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    Json::object([
+        ("line", before.matches('\n').count().into()),
+        (
+            "character",
+            before[line_start..].encode_utf16().count().into(),
+        ),
+    ])
+}
+
+/// The byte offset of an LSP position, clamped to the end of its line.
+fn offset(source: &str, position: &Json) -> Option<usize> {
+    let line = usize::try_from(position.get("line")?.as_i64()?).ok()?;
+    let character = usize::try_from(position.get("character")?.as_i64()?).ok()?;
+    let line_start = match line {
+        0 => 0,
+        _ => source.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+    let text = &source[line_start..];
+    let text = &text[..text.find('\n').unwrap_or(text.len())];
+    let mut units = 0;
+    for (idx, ch) in text.char_indices() {
+        if units >= character {
+            return Some(line_start + idx);
+        }
+        units += ch.len_utf16();
+    }
+    Some(line_start + text.len())
+}
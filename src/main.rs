@@ -1,6 +1,15 @@
-fn main() {
-    if let Err(err) = satukitan_rs::cli::run() {
-        eprintln!("Error: {err}");
-        std::process::exit(1);
+use std::process::ExitCode;
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: satukitan_rs::bench::CountingAllocator = satukitan_rs::bench::CountingAllocator;
+
+fn main() -> ExitCode {
+    match satukitan_rs::cli::run() {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
     }
 }
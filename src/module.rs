@@ -0,0 +1,202 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::ast::Program;
+use crate::env::Environment;
+use crate::error::SatukitanError;
+use crate::io::FsAccess;
+use crate::parser::parse_program;
+use crate::resolver::collect_definitions;
+use crate::symbol::Symbol;
+use crate::value::Value;
+
+/// An `import` form: the module path as written and an optional namespace
+/// prefix replacing the file stem.
+#[derive(Debug)]
+pub struct Import {
+    pub path: String,
+    pub alias: Option<String>,
+}
+
+/// The bindings a module exports, without their prefix.
+type Exports = Rc<Vec<(String, Value)>>;
+
+/// Loads `.st` modules for `import`, shared by an interpreter and every
+/// module it loads.
+///
+/// A module path is resolved against the directory of the importing file
+/// (the working directory for code that is not in a file), then against each
+/// search path. Every module runs once, in its own root environment that
+/// starts with the interpreter's builtins; later imports reuse its exports.
+#[derive(Debug)]
+pub struct ModuleLoader {
+    base: RefCell<Vec<(Symbol, Value)>>,
+    search_paths: RefCell<Vec<PathBuf>>,
+    entry_dir: RefCell<Option<PathBuf>>,
+    loading: RefCell<Vec<PathBuf>>,
+    cache: RefCell<HashMap<PathBuf, Exports>>,
+    fs_access: FsAccess,
+}
+
+impl ModuleLoader {
+    pub fn new(base: Vec<(Symbol, Value)>, fs_access: FsAccess) -> Self {
+        Self {
+            base: RefCell::new(base),
+            search_paths: RefCell::new(Vec::new()),
+            entry_dir: RefCell::new(None),
+            loading: RefCell::new(Vec::new()),
+            cache: RefCell::new(HashMap::new()),
+            fs_access,
+        }
+    }
+
+    /// Makes `name` available in modules loaded from now on.
+    pub fn define_base(&self, name: Symbol, value: Value) {
+        let mut base = self.base.borrow_mut();
+        base.retain(|(existing, _)| *existing != name);
+        base.push((name, value));
+    }
+
+    /// Stops offering `name` to modules loaded from now on.
+    pub(crate) fn remove_base(&self, name: Symbol) {
+        self.base
+            .borrow_mut()
+            .retain(|(existing, _)| *existing != name);
+    }
+
+    /// Replaces the base bindings and forgets every loaded module.
+    pub(crate) fn reset(&self, base: Vec<(Symbol, Value)>) {
+        *self.base.borrow_mut() = base;
+        self.cache.borrow_mut().clear();
+    }
+
+    pub fn add_search_path(&self, dir: impl Into<PathBuf>) {
+        self.search_paths.borrow_mut().push(dir.into());
+    }
+
+    /// Sets the directory that imports outside any module are relative to,
+    /// returning the previous one.
+    pub fn set_entry_dir(&self, dir: Option<PathBuf>) -> Option<PathBuf> {
+        self.entry_dir.replace(dir)
+    }
+
+    fn resolve(&self, spec: &str) -> Result<PathBuf, SatukitanError> {
+        let mut relative = PathBuf::from(spec);
+        match relative.extension() {
+            None => {
+                relative.set_extension("st");
+            }
+            Some(ext) if ext == "st" => {}
+            Some(_) => {
+                return Err(SatukitanError::Import(format!(
+                    "module '{spec}' is not a .st file"
+                )));
+            }
+        }
+        let base = match self.loading.borrow().last() {
+            Some(importer) => importer.parent().map(Path::to_path_buf),
+            None => self.entry_dir.borrow().clone(),
+        };
+        let base = base.unwrap_or_default();
+        let search_paths = self.search_paths.borrow();
+        std::iter::once(base.as_path())
+            .chain(search_paths.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(&relative))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| SatukitanError::Import(format!("module '{spec}' not found")))?
+            .canonicalize()
+            .map_err(SatukitanError::from)
+    }
+
+    fn cycle_error(&self, path: &Path) -> SatukitanError {
+        let loading = self.loading.borrow();
+        let start = loading.iter().position(|p| p == path).unwrap_or(0);
+        let chain = loading[start..]
+            .iter()
+            .chain(std::iter::once(&path.to_path_buf()))
+            .map(|p| display_name(p))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        SatukitanError::Import(format!("import cycle: {chain}"))
+    }
+
+    fn load(
+        self: &Rc<Self>,
+        path: PathBuf,
+        run: impl FnOnce(&Program, Rc<RefCell<Environment>>) -> Result<Value, SatukitanError>,
+    ) -> Result<Exports, SatukitanError> {
+        if let Some(exports) = self.cache.borrow().get(&path) {
+            return Ok(exports.clone());
+        }
+        if self.loading.borrow().contains(&path) {
+            return Err(self.cycle_error(&path));
+        }
+        let source = fs::read_to_string(&path)?;
+        let program = parse_program(&source)?;
+
+        let mut env = Environment::new();
+        for (name, value) in self.base.borrow().iter() {
+            env.define_symbol(*name, value.clone());
+        }
+        env.set_modules(self);
+        let env = Rc::new(RefCell::new(env));
+
+        self.loading.borrow_mut().push(path.clone());
+        let result = run(&program, env.clone());
+        self.loading.borrow_mut().pop();
+        result?;
+
+        let mut names = Vec::new();
+        collect_definitions(&program, &mut names);
+        let env = env.borrow();
+        let exports = names
+            .into_iter()
+            .filter(|name| !name.as_str().starts_with('_'))
+            .filter_map(|name| Some((name.to_string(), env.get_symbol(name)?)))
+            .collect::<Vec<_>>();
+        let exports = Rc::new(exports);
+        self.cache.borrow_mut().insert(path, exports.clone());
+        Ok(exports)
+    }
+}
+
+/// Runs an `import` form for code executing in `env`: loads the module with
+/// `run` unless it is cached, then binds each export as `prefix.name` in the
+/// root environment of `env`.
+pub fn import(
+    import: &Import,
+    env: &Rc<RefCell<Environment>>,
+    run: impl FnOnce(&Program, Rc<RefCell<Environment>>) -> Result<Value, SatukitanError>,
+) -> Result<Value, SatukitanError> {
+    let root = Environment::root(env);
+    let loader = root
+        .borrow()
+        .modules()
+        .ok_or_else(|| SatukitanError::Import("modules are not available here".to_string()))?;
+    loader.fs_access.check()?;
+
+    let path = loader.resolve(&import.path)?;
+    let prefix = match &import.alias {
+        Some(alias) => alias.clone(),
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let exports = loader.load(path, run)?;
+
+    let mut root = root.borrow_mut();
+    for (name, value) in exports.iter() {
+        root.define(format!("{prefix}.{name}"), value.clone());
+    }
+    Ok(Value::Nil)
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -0,0 +1,343 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Expr, Program};
+use crate::builtins;
+use crate::env::Environment;
+use crate::resolver::extract_body;
+use crate::value::{BuiltinFunction, Value};
+
+/// Rewrites `program` so that work which only depends on literals happens
+/// once, before evaluation:
+///
+/// - calls to pure builtins whose arguments are all literals are folded,
+/// - `nobu` with a literal condition is replaced by the chosen branch,
+/// - calls with literal arguments to trivial functions are inlined.
+///
+/// A builtin is only folded while `env` still binds it to the pure builtin and
+/// the program never rebinds its name. Inlined functions are assumed not to be
+/// redefined by later programs evaluated in the same environment.
+pub fn optimize_program(program: &[Expr], env: &Environment) -> Program {
+    let mut bound = HashMap::new();
+    count_bindings(program, &mut bound);
+
+    let builtins = builtins::PURE
+        .iter()
+        .filter(|name| !bound.contains_key(**name))
+        .filter_map(|name| match env.get(name) {
+            Some(Value::Builtin(builtin)) if builtin.name == *name && builtin.is_pure() => {
+                Some((*name, builtin))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut optimizer = Optimizer {
+        builtins,
+        functions: HashMap::new(),
+    };
+
+    let mut optimized = Vec::with_capacity(program.len());
+    for expr in program {
+        let expr = optimizer.expr(expr);
+        if let Some((name, function)) = trivial_function(&expr, &bound, env, &optimizer) {
+            optimizer.functions.insert(name, function);
+        }
+        optimized.push(expr);
+    }
+    optimized
+}
+
+/// A single-expression function that only uses its parameters, literals and
+/// foldable builtins, so a call with literal arguments has a literal result.
+struct TrivialFunction {
+    params: Vec<String>,
+    body: Expr,
+}
+
+struct Optimizer {
+    builtins: HashMap<&'static str, BuiltinFunction>,
+    functions: HashMap<String, TrivialFunction>,
+}
+
+impl Optimizer {
+    fn expr(&self, expr: &Expr) -> Expr {
+        match expr {
+            Expr::ListLiteral(items) => Expr::ListLiteral(self.exprs(items)),
+            Expr::List(items) => match items.split_first() {
+                Some((Expr::Symbol(name), args)) => self
+                    .application(name, args)
+                    .unwrap_or_else(|args| Expr::List(with_head(name, args))),
+                _ => Expr::List(self.exprs(items)),
+            },
+            Expr::Call { func, args } => match func.as_ref() {
+                Expr::Symbol(name) => {
+                    self.application(name, args)
+                        .unwrap_or_else(|args| Expr::Call {
+                            func: func.clone(),
+                            args,
+                        })
+                }
+                other => Expr::Call {
+                    func: Box::new(self.expr(other)),
+                    args: self.exprs(args),
+                },
+            },
+            other => other.clone(),
+        }
+    }
+
+    fn exprs(&self, exprs: &[Expr]) -> Vec<Expr> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    /// Returns the replacement for an application, or its optimised
+    /// arguments when it has to stay a call.
+    fn application(&self, name: &str, args: &[Expr]) -> Result<Expr, Vec<Expr>> {
+        match (name, args) {
+            ("nobu", [condition, then, otherwise]) => {
+                let condition = self.expr(condition);
+                match condition {
+                    Expr::Bool(true) => Ok(self.expr(then)),
+                    Expr::Bool(false) => Ok(self.expr(otherwise)),
+                    _ => Err(vec![condition, self.expr(then), self.expr(otherwise)]),
+                }
+            }
+            ("gakas", [target, value]) => Err(vec![target.clone(), self.expr(value)]),
+            ("gakasdenu", [target, params, body]) => {
+                Err(vec![target.clone(), params.clone(), self.expr(body)])
+            }
+            ("nobu" | "gakas" | "gakasdenu" | "import", _) => Err(args.to_vec()),
+            _ => {
+                let args = self.arguments(args);
+                if let Some(folded) = self.fold(name, &args) {
+                    Ok(folded)
+                } else if let Some(inlined) = self.inline(name, &args) {
+                    Ok(inlined)
+                } else {
+                    Err(args)
+                }
+            }
+        }
+    }
+
+    /// Optimises call arguments, folding a builtin argument together with the
+    /// literals it would consume at run time. Folding stops at the first
+    /// argument whose value is unknown, since it could itself be a builtin
+    /// consuming what follows.
+    fn arguments(&self, args: &[Expr]) -> Vec<Expr> {
+        let args = self.exprs(args);
+        let mut folded = Vec::with_capacity(args.len());
+        let mut index = 0;
+        while index < args.len() {
+            let arg = &args[index];
+            if is_literal(arg) {
+                folded.push(arg.clone());
+                index += 1;
+                continue;
+            }
+            let group = arg.as_symbol().and_then(|name| {
+                let builtin = self.builtins.get(name)?;
+                let required = builtin.arity().min();
+                let operands = args.get(index + 1..index + 1 + required)?;
+                if required == 0 || !operands.iter().all(is_literal) {
+                    return None;
+                }
+                Some((self.fold(name, operands)?, required))
+            });
+            match group {
+                Some((value, consumed)) => {
+                    folded.push(value);
+                    index += 1 + consumed;
+                }
+                None => {
+                    folded.extend_from_slice(&args[index..]);
+                    break;
+                }
+            }
+        }
+        folded
+    }
+
+    fn fold(&self, name: &str, args: &[Expr]) -> Option<Expr> {
+        let builtin = self.builtins.get(name)?;
+        let values = args.iter().map(literal_value).collect::<Option<Vec<_>>>()?;
+        builtin
+            .call(&values)
+            .ok()
+            .and_then(|value| value_literal(&value))
+    }
+
+    fn inline(&self, name: &str, args: &[Expr]) -> Option<Expr> {
+        let function = self.functions.get(name)?;
+        if function.params.len() != args.len() || !args.iter().all(is_literal) {
+            return None;
+        }
+        let bindings = function
+            .params
+            .iter()
+            .map(String::as_str)
+            .zip(args)
+            .collect::<HashMap<_, _>>();
+        let inlined = self.expr(&substitute(&function.body, &bindings));
+        is_literal(&inlined).then_some(inlined)
+    }
+}
+
+fn trivial_function(
+    expr: &Expr,
+    bound: &HashMap<String, usize>,
+    env: &Environment,
+    optimizer: &Optimizer,
+) -> Option<(String, TrivialFunction)> {
+    let (name, params, body) = match definition(expr)? {
+        (Some("gakasdenu"), [Expr::Symbol(name), Expr::List(params), body]) => (name, params, body),
+        _ => return None,
+    };
+    if bound.get(name) != Some(&1) || env.get(name).is_some() {
+        return None;
+    }
+    let params = params
+        .iter()
+        .map(|param| param.as_symbol().map(str::to_string))
+        .collect::<Option<Vec<_>>>()?;
+    let [body] = extract_body(body).try_into().ok()?;
+
+    let params_set: HashSet<&str> = params.iter().map(String::as_str).collect();
+    if !is_trivial_body(&body, &params_set, optimizer) {
+        return None;
+    }
+    Some((name.clone(), TrivialFunction { params, body }))
+}
+
+fn definition(expr: &Expr) -> Option<(Option<&str>, &[Expr])> {
+    match expr {
+        Expr::List(items) => {
+            let (head, args) = items.split_first()?;
+            Some((head.as_symbol(), args))
+        }
+        Expr::Call { func, args } => Some((func.as_symbol(), args.as_slice())),
+        _ => None,
+    }
+}
+
+/// Counts every `gakas`/`gakasdenu` target and parameter name in `exprs`.
+fn count_bindings(exprs: &[Expr], bound: &mut HashMap<String, usize>) {
+    for expr in exprs {
+        match definition(expr) {
+            Some((Some("gakas"), [Expr::Symbol(name), value])) => {
+                *bound.entry(name.clone()).or_default() += 1;
+                count_bindings(std::slice::from_ref(value), bound);
+            }
+            Some((Some("gakasdenu"), [Expr::Symbol(name), params, body])) => {
+                *bound.entry(name.clone()).or_default() += 1;
+                for param in params.list_items().unwrap_or_default() {
+                    if let Some(param) = param.as_symbol() {
+                        *bound.entry(param.to_string()).or_default() += 1;
+                    }
+                }
+                count_bindings(std::slice::from_ref(body), bound);
+            }
+            _ => {
+                if let Some(items) = expr.list_items() {
+                    count_bindings(items, bound);
+                } else if let Expr::Call { func, args } = expr {
+                    count_bindings(std::slice::from_ref(func.as_ref()), bound);
+                    count_bindings(args, bound);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `expr` only reads `params`, literals and foldable builtins. Only
+/// builtins and `nobu` may be applied, so substituting literals for the
+/// parameters never turns a value into a callee.
+fn is_trivial_body(expr: &Expr, params: &HashSet<&str>, optimizer: &Optimizer) -> bool {
+    let trivial = |items: &[Expr]| {
+        items
+            .iter()
+            .all(|item| is_trivial_body(item, params, optimizer))
+    };
+    let applicable = |name: &str| name == "nobu" || optimizer.builtins.contains_key(name);
+    match expr {
+        Expr::Number(_) | Expr::Bool(_) | Expr::String(_) => true,
+        Expr::Symbol(name) => params.contains(name.as_str()) || applicable(name),
+        Expr::ListLiteral(items) => trivial(items),
+        Expr::List(items) => match items.split_first() {
+            None => true,
+            Some((Expr::Symbol(name), [])) => params.contains(name.as_str()) || applicable(name),
+            Some((Expr::Symbol(name), args)) => applicable(name) && trivial(args),
+            Some(_) => trivial(items),
+        },
+        Expr::Call { func, args } => func.as_symbol().is_some_and(applicable) && trivial(args),
+    }
+}
+
+fn substitute(expr: &Expr, bindings: &HashMap<&str, &Expr>) -> Expr {
+    match expr {
+        Expr::Symbol(name) => bindings
+            .get(name.as_str())
+            .map(|value| (*value).clone())
+            .unwrap_or_else(|| expr.clone()),
+        Expr::List(items) => Expr::List(
+            items
+                .iter()
+                .map(|item| substitute(item, bindings))
+                .collect(),
+        ),
+        Expr::ListLiteral(items) => Expr::ListLiteral(
+            items
+                .iter()
+                .map(|item| substitute(item, bindings))
+                .collect(),
+        ),
+        Expr::Call { func, args } => Expr::Call {
+            func: func.clone(),
+            args: args.iter().map(|arg| substitute(arg, bindings)).collect(),
+        },
+        other => other.clone(),
+    }
+}
+
+fn with_head(name: &str, args: Vec<Expr>) -> Vec<Expr> {
+    let mut items = Vec::with_capacity(args.len() + 1);
+    items.push(Expr::Symbol(name.to_string()));
+    items.extend(args);
+    items
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Bool(_) | Expr::String(_) => true,
+        Expr::ListLiteral(items) => items.iter().all(is_literal),
+        _ => false,
+    }
+}
+
+fn literal_value(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Number(n) => Some(Value::Number(*n)),
+        Expr::Bool(b) => Some(Value::Bool(*b)),
+        Expr::String(s) => Some(Value::from(s.as_str())),
+        Expr::ListLiteral(items) => items
+            .iter()
+            .map(literal_value)
+            .collect::<Option<Vec<_>>>()
+            .map(Value::from),
+        _ => None,
+    }
+}
+
+fn value_literal(value: &Value) -> Option<Expr> {
+    match value {
+        Value::Number(n) => Some(Expr::Number(*n)),
+        Value::Bool(b) => Some(Expr::Bool(*b)),
+        Value::String(s) => Some(Expr::String(s.to_string())),
+        Value::List(items) => items
+            .iter()
+            .map(value_literal)
+            .collect::<Option<Vec<_>>>()
+            .map(Expr::ListLiteral),
+        _ => None,
+    }
+}
@@ -1,159 +1,228 @@
 use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
-use rustyline::highlight::Highlighter;
+use rustyline::highlight::{CmdKind, Highlighter};
 use rustyline::hint::Hinter;
-use rustyline::validate::Validator;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
 use rustyline::{Context, Helper};
 use rustyline::{Editor, history::DefaultHistory};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::env;
 use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::time::Instant;
 
+use crate::cli::validate_extension;
+use crate::env::Environment;
 use crate::error::SatukitanError;
 use crate::interpreter::Interpreter;
-use crate::value::Value;
+use crate::lexer::{Token, TokenKind, tokenize};
+use crate::parser::{Completeness, completeness, parse_program};
+use crate::resolver::SPECIAL_FORMS;
+use crate::value::{BuiltinFunction, Value, bool_token, parse_number_token};
 
-struct KeywordInfo {
-    name: &'static str,
-    annotation: Option<&'static str>,
+#[derive(Clone)]
+pub(crate) struct KeywordInfo {
+    pub(crate) name: &'static str,
+    pub(crate) annotation: Option<Cow<'static, str>>,
+    pub(crate) doc: Option<Cow<'static, str>>,
+}
+
+impl KeywordInfo {
+    const fn builtin(name: &'static str, annotation: &'static str) -> Self {
+        Self {
+            name,
+            annotation: Some(Cow::Borrowed(annotation)),
+            doc: None,
+        }
+    }
+
+    /// Describes a root binding: the parameter list of a function, the type
+    /// of any other value.
+    fn binding(name: &'static str, value: &Value) -> Self {
+        let (annotation, doc) = match value {
+            Value::Builtin(builtin) => return Self::host(builtin),
+            Value::Function(function) => (format!("({})", function.params().join(" ")), None),
+            other => (format!("({})", other.type_name()), None),
+        };
+        Self {
+            name,
+            annotation: Some(Cow::Owned(annotation)),
+            doc,
+        }
+    }
+
+    fn host(builtin: &BuiltinFunction) -> Self {
+        Self {
+            name: builtin.name,
+            annotation: builtin.annotation().map(|a| Cow::Owned(a.to_string())),
+            doc: builtin.doc().map(|d| Cow::Owned(d.to_string())),
+        }
+    }
 }
 
 const KEYWORD_INFOS: &[KeywordInfo] = &[
-    KeywordInfo {
-        name: "#ta",
-        annotation: Some("(10)"),
-    },
-    KeywordInfo {
-        name: "ditas",
-        annotation: Some("(num num -> bool)"),
-    },
-    KeywordInfo {
-        name: "ditasgata",
-        annotation: Some("(num num -> bool)"),
-    },
-    KeywordInfo {
-        name: "fanitas",
-        annotation: Some("(list -> list)"),
-    },
-    KeywordInfo {
-        name: "fityes",
-        annotation: Some("(num num -> bool)"),
-    },
-    KeywordInfo {
-        name: "fityesgata",
-        annotation: Some("(num num -> bool)"),
-    },
-    KeywordInfo {
-        name: "ga",
-        annotation: Some("(false)"),
-    },
-    KeywordInfo {
-        name: "gakas",
-        annotation: Some("(symbol value -> value)"),
-    },
-    KeywordInfo {
-        name: "gakasdenu",
-        annotation: Some("(symbol (params) body -> function)"),
-    },
-    KeywordInfo {
-        name: "gatas",
-        annotation: Some("(value value -> bool)"),
-    },
-    KeywordInfo {
-        name: "kenus",
-        annotation: Some("(bool bool -> bool)"),
-    },
-    KeywordInfo {
-        name: "matyes",
-        annotation: Some("(num num -> num)"),
-    },
-    KeywordInfo {
-        name: "me",
-        annotation: Some("(true)"),
-    },
-    KeywordInfo {
-        name: "nitas",
-        annotation: Some("(num num -> num)"),
-    },
-    KeywordInfo {
-        name: "nobu",
-        annotation: Some("(bool then else -> value)"),
-    },
-    KeywordInfo {
-        name: "ra",
-        annotation: Some("(2)"),
-    },
-    KeywordInfo {
-        name: "rakas",
-        annotation: Some("(list -> num)"),
-    },
-    KeywordInfo {
-        name: "re",
-        annotation: Some("(4)"),
-    },
-    KeywordInfo {
-        name: "ri",
-        annotation: Some("(5)"),
-    },
-    KeywordInfo {
-        name: "ritas",
-        annotation: Some("(num num -> num)"),
-    },
-    KeywordInfo {
-        name: "ro",
-        annotation: Some("(3)"),
-    },
-    KeywordInfo {
-        name: "ru",
-        annotation: Some("(1)"),
-    },
-    KeywordInfo {
-        name: "rv",
-        annotation: Some("(0)"),
-    },
-    KeywordInfo {
-        name: "rya",
-        annotation: Some("(6)"),
-    },
-    KeywordInfo {
-        name: "rye",
-        annotation: Some("(9)"),
-    },
-    KeywordInfo {
-        name: "ryo",
-        annotation: Some("(8)"),
-    },
-    KeywordInfo {
-        name: "ryu",
-        annotation: Some("(7)"),
-    },
-    KeywordInfo {
-        name: "sipus",
-        annotation: Some("(value -> nil)"),
-    },
-    KeywordInfo {
-        name: "teses",
-        annotation: Some("(bool bool -> bool)"),
-    },
+    KeywordInfo::builtin("#ta", "(10)"),
+    KeywordInfo::builtin("append-file", "(path str -> nil)"),
+    KeywordInfo::builtin("args", "(list)"),
+    KeywordInfo::builtin("cons", "(value list -> list)"),
+    KeywordInfo::builtin("ditas", "(num num -> bool)"),
+    KeywordInfo::builtin("ditasgata", "(num num -> bool)"),
+    KeywordInfo::builtin("exit", "(num -> nil)"),
+    KeywordInfo::builtin("fanitas", "(list -> list)"),
+    KeywordInfo::builtin("file-exists", "(path -> bool)"),
+    KeywordInfo::builtin("fityes", "(num num -> bool)"),
+    KeywordInfo::builtin("fityesgata", "(num num -> bool)"),
+    KeywordInfo::builtin("ga", "(false)"),
+    KeywordInfo::builtin("gakas", "(symbol value -> value)"),
+    KeywordInfo::builtin("gakasdenu", "(symbol (params) body -> function)"),
+    KeywordInfo::builtin("gatas", "(value value -> bool)"),
+    KeywordInfo::builtin("getenv", "(str -> str)"),
+    KeywordInfo::builtin("head", "(list -> value)"),
+    KeywordInfo::builtin("import", "(str [prefix] -> nil)"),
+    KeywordInfo::builtin("json-parse", "(str -> value)"),
+    KeywordInfo::builtin("json-stringify", "(value -> str)"),
+    KeywordInfo::builtin("kenus", "(bool bool -> bool)"),
+    KeywordInfo::builtin("lines-iterator", "(-> (-> str))"),
+    KeywordInfo::builtin("list-dir", "(path -> list)"),
+    KeywordInfo::builtin("matyes", "(num num -> num)"),
+    KeywordInfo::builtin("me", "(true)"),
+    KeywordInfo::builtin("nitas", "(num num -> num)"),
+    KeywordInfo::builtin("nobu", "(bool then else -> value)"),
+    KeywordInfo::builtin("ra", "(2)"),
+    KeywordInfo::builtin("rakas", "(list -> num)"),
+    KeywordInfo::builtin("re", "(4)"),
+    KeywordInfo::builtin("read-all", "(-> str)"),
+    KeywordInfo::builtin("read-file", "(path -> str)"),
+    KeywordInfo::builtin("range", "(num num -> list)"),
+    KeywordInfo::builtin("read-line", "(-> str)"),
+    KeywordInfo::builtin("ri", "(5)"),
+    KeywordInfo::builtin("ritas", "(num num -> num)"),
+    KeywordInfo::builtin("ro", "(3)"),
+    KeywordInfo::builtin("ru", "(1)"),
+    KeywordInfo::builtin("rv", "(0)"),
+    KeywordInfo::builtin("rya", "(6)"),
+    KeywordInfo::builtin("rye", "(9)"),
+    KeywordInfo::builtin("ryo", "(8)"),
+    KeywordInfo::builtin("ryu", "(7)"),
+    KeywordInfo::builtin("sipus", "(value -> nil)"),
+    KeywordInfo::builtin("sum", "(list -> num)"),
+    KeywordInfo::builtin("tail", "(list -> list)"),
+    KeywordInfo::builtin("teses", "(bool bool -> bool)"),
+    KeywordInfo::builtin("write-file", "(path str -> nil)"),
 ];
 
+const PROMPT: &str = "satukitan> ";
+/// Prompt for the lines that continue an unfinished expression.
+const CONTINUATION_PROMPT: &str = "      ...> ";
 const MAX_HINT_SUGGESTIONS: usize = 5;
 const HEADER_COLOR: &str = "[38;5;39m";
 const CANDIDATE_COLOR: &str = "[38;5;214m";
 const DIVIDER_COLOR: &str = "[38;5;240m";
+const NUMBER_COLOR: &str = "[38;5;141m";
+const BOOL_COLOR: &str = "[38;5;208m";
+const STRING_COLOR: &str = "[38;5;114m";
+const SPECIAL_FORM_COLOR: &str = "[1;38;5;204m";
+const BUILTIN_COLOR: &str = "[38;5;39m";
+const COMMENT_COLOR: &str = "[38;5;244m";
+const MATCHING_BRACKET_COLOR: &str = "[1;38;5;226m";
 
+/// Rustyline helper providing completion, hints, highlighting and
+/// multi-line validation for the REPL.
 #[derive(Clone)]
-struct SatukitanHelper {
-    keywords: &'static [KeywordInfo],
+pub struct SatukitanHelper {
+    keywords: Vec<KeywordInfo>,
+    /// Root environment of the session, queried for the names it binds.
+    env: Rc<RefCell<Environment>>,
+    /// Lines of the unfinished expression entered so far.
+    pending: RefCell<String>,
+    /// Whether to highlight the bracket matching the one at the cursor. Off
+    /// for the final rendering of an accepted line.
+    show_matching: Cell<bool>,
 }
 
-impl Default for SatukitanHelper {
-    fn default() -> Self {
+impl SatukitanHelper {
+    /// Completes the language keywords, the host functions registered with
+    /// `interpreter` and whatever its root environment binds at the time.
+    pub fn new(interpreter: &Interpreter) -> Self {
+        let mut keywords = KEYWORD_INFOS.to_vec();
+        for builtin in interpreter.host_functions() {
+            keywords.retain(|info| info.name != builtin.name);
+            keywords.push(KeywordInfo::host(builtin));
+        }
         Self {
-            keywords: KEYWORD_INFOS,
+            keywords,
+            env: interpreter.environment(),
+            pending: RefCell::new(String::new()),
+            show_matching: Cell::new(true),
+        }
+    }
+
+    /// Keywords merged with the current root bindings, sorted by name.
+    /// Keywords keep their hand-written annotations.
+    pub(crate) fn entries(&self) -> Vec<KeywordInfo> {
+        let mut entries = self.keywords.clone();
+        for (name, value) in self.env.borrow().bindings() {
+            let name = name.as_str();
+            if !self.keywords.iter().any(|info| info.name == name) {
+                entries.push(KeywordInfo::binding(name, &value));
+            }
+        }
+        entries.sort_by_key(|info| info.name);
+        entries
+    }
+
+    /// One-line description of a binding for `:env` and `:type`, e.g.
+    /// `add-two : function (x y)` or `x : number = ra`.
+    fn describe(&self, name: &str, value: &Value) -> String {
+        let annotation = match value {
+            Value::Builtin(builtin) => self
+                .keywords
+                .iter()
+                .find(|info| info.name == builtin.name)
+                .and_then(|info| info.annotation.clone())
+                .or_else(|| builtin.annotation().map(|a| Cow::Owned(a.to_string()))),
+            Value::Function(function) => {
+                Some(Cow::Owned(format!("({})", function.params().join(" "))))
+            }
+            other => return format!("{name} : {} = {other}", other.type_name()),
+        };
+        match annotation {
+            Some(annotation) => format!("{name} : {} {annotation}", value.type_name()),
+            None => format!("{name} : {}", value.type_name()),
+        }
+    }
+
+    fn builtin_names(&self) -> HashSet<&'static str> {
+        self.env
+            .borrow()
+            .bindings()
+            .into_iter()
+            .filter_map(|(_, value)| match value {
+                Value::Builtin(builtin) => Some(builtin.name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn token_color(&self, token: &Token, builtins: &HashSet<&str>) -> Option<&'static str> {
+        match token.kind {
+            TokenKind::Identifier if parse_number_token(token.text).is_some() => Some(NUMBER_COLOR),
+            TokenKind::Identifier if bool_token(token.text).is_some() => Some(BOOL_COLOR),
+            TokenKind::Identifier if SPECIAL_FORMS.contains(&token.text) => {
+                Some(SPECIAL_FORM_COLOR)
+            }
+            TokenKind::Identifier if builtins.contains(token.text) => Some(BUILTIN_COLOR),
+            TokenKind::String => Some(STRING_COLOR),
+            TokenKind::Comment => Some(COMMENT_COLOR),
+            _ => None,
         }
     }
-}
 
-impl SatukitanHelper {
     fn fragment_start(&self, line: &str, pos: usize) -> usize {
         line[..pos]
             .char_indices()
@@ -163,37 +232,40 @@ impl SatukitanHelper {
             .unwrap_or(0)
     }
 
-    fn matching_keywords(&self, fragment: &str) -> Vec<&'static KeywordInfo> {
-        self.keywords
-            .iter()
+    fn matching_keywords(&self, fragment: &str) -> Vec<KeywordInfo> {
+        self.entries()
+            .into_iter()
             .filter(|info| info.name.starts_with(fragment))
-            .collect::<Vec<_>>()
+            .collect()
     }
 
     fn candidate_pairs(&self, fragment: &str) -> Vec<Pair> {
         self.matching_keywords(fragment)
             .into_iter()
             .map(|info| Pair {
-                display: self.label_for(info),
+                display: self.label_for(&info),
                 replacement: info.name.to_string(),
             })
             .collect()
     }
 
     fn label_for(&self, info: &KeywordInfo) -> String {
-        if let Some(annotation) = info.annotation {
+        if let Some(annotation) = &info.annotation {
             format!("{}{}", info.name, annotation)
         } else {
             info.name.to_string()
         }
     }
 
-    fn format_hint(&self, fragment: &str, matches: &[&'static KeywordInfo]) -> Option<String> {
+    fn format_hint(&self, fragment: &str, matches: &[KeywordInfo]) -> Option<String> {
         if matches.is_empty() {
             return None;
         }
-        if matches.len() == 1 && matches[0].name == fragment {
-            return None;
+        if let [info] = matches
+            && info.name == fragment
+        {
+            let doc = info.doc.as_ref()?;
+            return Some(format!("\n{DIVIDER_COLOR}{doc}\x1b[0m"));
         }
 
         let mut buffer = String::new();
@@ -255,39 +327,285 @@ impl Hinter for SatukitanHelper {
     }
 }
 
-impl Highlighter for SatukitanHelper {}
+impl Highlighter for SatukitanHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        let tokens = tokenize(line);
+        let builtins = self.builtin_names();
+        let matching = self
+            .show_matching
+            .get()
+            .then(|| matching_brackets(&tokens, pos))
+            .flatten();
+        let mut highlighted = String::with_capacity(line.len() * 2);
+        for (idx, token) in tokens.iter().enumerate() {
+            let color = if matching.is_some_and(|(open, close)| idx == open || idx == close) {
+                Some(MATCHING_BRACKET_COLOR)
+            } else {
+                self.token_color(token, &builtins)
+            };
+            match color {
+                Some(color) => {
+                    let _ = write!(highlighted, "{color}{}[0m", token.text);
+                }
+                None => highlighted.push_str(token.text),
+            }
+        }
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        self.show_matching.set(kind != CmdKind::ForcedRefresh);
+        true
+    }
+}
 
-impl Validator for SatukitanHelper {}
+/// Rejects mismatched closing brackets in the expression being entered.
+/// Unclosed brackets and strings are accepted and continued by [`start`]
+/// under the continuation prompt.
+impl Validator for SatukitanHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let pending = self.pending.borrow();
+        let source = if pending.is_empty() {
+            Cow::Borrowed(ctx.input())
+        } else {
+            Cow::Owned(format!("{pending}\n{}", ctx.input()))
+        };
+        Ok(match completeness(&source) {
+            Completeness::Invalid(message) => {
+                ValidationResult::Invalid(Some(format!("\n{DIVIDER_COLOR}{message}\x1b[0m")))
+            }
+            Completeness::Complete | Completeness::Incomplete => ValidationResult::Valid(None),
+        })
+    }
+}
 
 impl Helper for SatukitanHelper {}
 
-pub fn start(interpreter: &mut Interpreter) -> Result<(), SatukitanError> {
+/// Runs the REPL until the user quits or a script calls `exit`, returning
+/// the exit code.
+pub fn start(interpreter: &mut Interpreter) -> Result<ExitCode, SatukitanError> {
     let mut rl = Editor::<SatukitanHelper, DefaultHistory>::new()
         .map_err(|err| SatukitanError::Eval(format!("repl init error: {err}")))?;
-    rl.set_helper(Some(SatukitanHelper::default()));
+    rl.set_helper(Some(SatukitanHelper::new(interpreter)));
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing file just means there is no history yet.
+        rl.load_history(path).ok();
+    }
 
+    let mut session = Vec::new();
+    let mut exit_code = ExitCode::SUCCESS;
+    let mut pending = String::new();
     loop {
-        match rl.readline("satukitan> ") {
+        let prompt = if pending.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match rl.readline(prompt) {
             Ok(line) => {
-                let trimmed = line.trim();
-                if trimmed.is_empty() {
-                    continue;
+                if pending.is_empty() {
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    if matches!(trimmed, "exit" | "quit") {
+                        break;
+                    }
+                    if let Some(command) = trimmed.strip_prefix(':') {
+                        rl.add_history_entry(trimmed).ok();
+                        match meta_command(interpreter, &mut session, command) {
+                            Ok(text) if text.is_empty() => {}
+                            Ok(text) => println!("{text}"),
+                            Err(SatukitanError::Exit(code)) => {
+                                exit_code = ExitCode::from(code);
+                                break;
+                            }
+                            Err(err) => eprintln!("Error: {err}"),
+                        }
+                        // `:reset` and `:load` change what the helper sees.
+                        rl.set_helper(Some(SatukitanHelper::new(interpreter)));
+                        continue;
+                    }
+                    pending.push_str(trimmed);
+                } else {
+                    pending.push('\n');
+                    pending.push_str(line.trim_end());
                 }
-                if matches!(trimmed, "exit" | "quit") {
-                    break;
+                if completeness(&pending) == Completeness::Incomplete {
+                    set_pending(&rl, &pending);
+                    continue;
                 }
-                rl.add_history_entry(trimmed).ok();
-                match interpreter.eval_str(trimmed) {
-                    Ok(value) => display_value(value),
+                let source = std::mem::take(&mut pending);
+                set_pending(&rl, &pending);
+                rl.add_history_entry(source.as_str()).ok();
+                match interpreter.eval_str(&source) {
+                    Ok(value) => {
+                        session.push(source);
+                        display_value(value);
+                    }
+                    Err(SatukitanError::Exit(code)) => {
+                        exit_code = ExitCode::from(code);
+                        break;
+                    }
                     Err(err) => eprintln!("Error: {err}"),
                 }
             }
+            Err(ReadlineError::Interrupted) if !pending.is_empty() => {
+                pending.clear();
+                set_pending(&rl, &pending);
+            }
             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
             Err(err) => return Err(SatukitanError::Eval(format!("REPL failure: {err}"))),
         }
     }
 
-    Ok(())
+    if let Some(path) = &history {
+        save_history(&mut rl, path);
+    }
+    Ok(exit_code)
+}
+
+const META_HELP: &str = "\
+:help                このヘルプを表示
+:env                 定義済みの名前(ビルトイン以外)を一覧表示
+:type <名前>         名前の型とシグネチャを表示
+:load <ファイル>     .st ファイルを読み込んで評価
+:save <ファイル>     成功した入力を .st スクリプトとして保存
+:restore <ファイル>  セッションを初期化して保存したスクリプトを読み込む
+:reset               セッションを初期状態に戻す
+:ast <式>            式の構文木を表示
+:time <式>           式を評価し、かかった時間を表示
+exit, quit           REPL を終了";
+
+/// Runs a `:` command given without its colon and returns the text to print.
+///
+/// `session` holds the inputs evaluated successfully so far, which `:save`
+/// writes out and `:load`, `:restore`, `:reset` and `:time` update.
+fn meta_command(
+    interpreter: &mut Interpreter,
+    session: &mut Vec<String>,
+    command: &str,
+) -> Result<String, SatukitanError> {
+    let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, rest)| (name, rest.trim()));
+    match (name, argument) {
+        ("help", _) => Ok(META_HELP.to_string()),
+        ("env", "") => {
+            let helper = SatukitanHelper::new(interpreter);
+            let lines = interpreter
+                .environment()
+                .borrow()
+                .bindings()
+                .into_iter()
+                .filter(|(_, value)| !matches!(value, Value::Builtin(_)))
+                .map(|(name, value)| helper.describe(name.as_str(), &value))
+                .collect::<Vec<_>>();
+            Ok(lines.join("\n"))
+        }
+        ("type", name) if !name.is_empty() => {
+            let value = interpreter
+                .environment()
+                .borrow()
+                .get(name)
+                .ok_or_else(|| SatukitanError::UndefinedSymbol(name.to_string()))?;
+            Ok(SatukitanHelper::new(interpreter).describe(name, &value))
+        }
+        ("load", path) if !path.is_empty() => load(interpreter, session, path),
+        ("save", path) if !path.is_empty() => {
+            validate_extension(Path::new(path))?;
+            let mut script = session.join("\n");
+            script.push('\n');
+            fs::write(path, script)?;
+            Ok(format!("saved {} inputs to {path}", session.len()))
+        }
+        ("restore", path) if !path.is_empty() => {
+            interpreter.reset();
+            session.clear();
+            load(interpreter, session, path)
+        }
+        ("reset", "") => {
+            interpreter.reset();
+            session.clear();
+            Ok(String::new())
+        }
+        ("ast", source) if !source.is_empty() => {
+            let program = parse_program(source)?;
+            let trees = program
+                .iter()
+                .map(|expr| format!("{expr:#?}"))
+                .collect::<Vec<_>>();
+            Ok(trees.join("\n"))
+        }
+        ("time", source) if !source.is_empty() => {
+            let started = Instant::now();
+            let value = interpreter.eval_str(source)?;
+            let elapsed = started.elapsed().as_secs_f64() * 1000.0;
+            session.push(source.to_string());
+            Ok(format!(
+                "{value}\n{DIVIDER_COLOR}time: {elapsed:.3} ms\x1b[0m"
+            ))
+        }
+        ("env" | "type" | "load" | "save" | "restore" | "reset" | "ast" | "time", _) => Err(
+            SatukitanError::Eval(format!(":{name}: wrong arguments (see :help)")),
+        ),
+        _ => Err(SatukitanError::Eval(format!(
+            "unknown command :{name} (see :help)"
+        ))),
+    }
+}
+
+/// Evaluates the file at `path` and records its source in `session`.
+fn load(
+    interpreter: &mut Interpreter,
+    session: &mut Vec<String>,
+    path: &str,
+) -> Result<String, SatukitanError> {
+    let value = interpreter.eval_file(path)?;
+    session.push(fs::read_to_string(path)?.trim_end().to_string());
+    Ok(if value.is_nil() {
+        String::new()
+    } else {
+        value.to_string()
+    })
+}
+
+/// File the REPL history is kept in: `satukitan/history.txt` under the
+/// platform's data directory.
+fn history_path() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let data_dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home().map(|home| home.join(".local/share")))
+    };
+    Some(data_dir?.join("satukitan").join("history.txt"))
+}
+
+fn save_history(rl: &mut Editor<SatukitanHelper, DefaultHistory>, path: &Path) {
+    let saved = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(ReadlineError::from)
+        .and_then(|()| rl.save_history(path));
+    if let Err(err) = saved {
+        eprintln!(
+            "Warning: could not save history to {}: {err}",
+            path.display()
+        );
+    }
+}
+
+fn set_pending(rl: &Editor<SatukitanHelper, DefaultHistory>, pending: &str) {
+    if let Some(helper) = rl.helper() {
+        pending.clone_into(&mut helper.pending.borrow_mut());
+    }
 }
 
 fn display_value(value: Value) {
@@ -296,6 +614,42 @@ fn display_value(value: Value) {
     }
 }
 
+/// Token indices of the bracket at or just before the cursor and its partner.
+fn matching_brackets(tokens: &[Token], pos: usize) -> Option<(usize, usize)> {
+    let is_bracket = |token: &Token| matches!(token.kind, TokenKind::Open | TokenKind::Close);
+    let at = tokens
+        .iter()
+        .position(|token| token.start == pos && is_bracket(token))
+        .or_else(|| {
+            tokens
+                .iter()
+                .position(|token| token.start + 1 == pos && is_bracket(token))
+        })?;
+    let mut depth = 0usize;
+    let partner = if tokens[at].kind == TokenKind::Open {
+        (at..tokens.len()).find(|&idx| {
+            match tokens[idx].kind {
+                TokenKind::Open => depth += 1,
+                TokenKind::Close => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })?
+    } else {
+        (0..=at).rev().find(|&idx| {
+            match tokens[idx].kind {
+                TokenKind::Close => depth += 1,
+                TokenKind::Open => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })?
+    };
+    let (open, close) = (at.min(partner), at.max(partner));
+    let pair = (tokens[open].text, tokens[close].text);
+    matches!(pair, ("(", ")") | ("[", "]")).then_some((open, close))
+}
+
 fn is_boundary(ch: char) -> bool {
     ch.is_whitespace() || matches!(ch, '(' | ')' | '[' | ']' | '{' | '}' | '"')
 }
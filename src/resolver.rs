@@ -0,0 +1,414 @@
+use std::cell::OnceCell;
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::compiler::Chunk;
+use crate::debugger::SourceLines;
+use crate::env::Environment;
+use crate::error::SatukitanError;
+use crate::module::Import;
+use crate::symbol::Symbol;
+use crate::value::Value;
+
+/// Names the resolver treats as special forms rather than applications.
+pub const SPECIAL_FORMS: &[&str] = &["nobu", "gakas", "gakasdenu", "import"];
+
+/// An expression whose variable references have been resolved to slots.
+///
+/// `depth` counts how many scopes to walk up from the scope the node runs in;
+/// `slot` indexes the bindings of that scope.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Const(Value),
+    Var {
+        depth: usize,
+        slot: usize,
+        name: Symbol,
+    },
+    ListLiteral(Vec<Node>),
+    Block(Vec<Node>),
+    /// Application of a symbol: `name` with no arguments yields the value
+    /// itself unless it is callable.
+    Apply {
+        callee: Box<Node>,
+        args: Vec<Node>,
+    },
+    Call {
+        func: Box<Node>,
+        args: Vec<Node>,
+    },
+    Nobu(Box<[Node; 3]>),
+    Gakas {
+        slot: usize,
+        value: Box<Node>,
+    },
+    Gakasdenu {
+        slot: usize,
+        lambda: Rc<Lambda>,
+    },
+    Import(Rc<Import>),
+    Malformed(Malformed),
+    /// A place the debugger may pause: a statement, or an expression that
+    /// starts a new line. Only emitted when resolving for the debugger.
+    Located {
+        line: usize,
+        node: Box<Node>,
+    },
+}
+
+/// A resolved `gakasdenu` body along with the slot layout of its frame.
+#[derive(Debug)]
+pub struct Lambda {
+    pub name: Symbol,
+    pub params: Vec<String>,
+    pub source: Vec<Expr>,
+    pub layout: Rc<Vec<Symbol>>,
+    pub body: Vec<Node>,
+    /// Bytecode for `body`, compiled the first time the VM calls it.
+    pub chunk: OnceCell<Rc<Chunk>>,
+}
+
+/// A special form whose shape is invalid. It is reported when evaluated, not
+/// when resolved, so earlier expressions still run.
+#[derive(Debug, Clone)]
+pub enum Malformed {
+    Arity {
+        form: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    Message(&'static str),
+}
+
+impl Malformed {
+    pub fn to_error(&self) -> SatukitanError {
+        match self {
+            Malformed::Arity {
+                form,
+                expected,
+                found,
+            } => SatukitanError::arity_exact(*form, *expected, *found),
+            Malformed::Message(message) => SatukitanError::Eval((*message).to_string()),
+        }
+    }
+}
+
+impl Lambda {
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+}
+
+/// Resolves a top-level program. Top-level bindings live in `env`, which
+/// gets a slot reserved for every free name the program mentions.
+pub fn resolve_program(program: &[Expr], env: &mut Environment) -> Vec<Node> {
+    let mut resolver = Resolver {
+        top: env,
+        scopes: Vec::new(),
+        lines: None,
+        line: 0,
+    };
+    program.iter().map(|expr| resolver.expr(expr)).collect()
+}
+
+/// Like [`resolve_program`], but wraps every statement of `program` and of
+/// its function bodies, and every expression starting a new line, in a
+/// [`Node::Located`] carrying its line in `lines`.
+pub(crate) fn resolve_located(
+    program: &[Expr],
+    lines: &SourceLines,
+    env: &mut Environment,
+) -> Vec<Node> {
+    let mut resolver = Resolver {
+        top: env,
+        scopes: Vec::new(),
+        lines: Some(lines),
+        line: 0,
+    };
+    program
+        .iter()
+        .map(|expr| resolver.statement(expr))
+        .collect()
+}
+
+struct Resolver<'a> {
+    top: &'a mut Environment,
+    scopes: Vec<Vec<Symbol>>,
+    /// Lines of the expressions being resolved, when resolving for the
+    /// debugger.
+    lines: Option<&'a SourceLines>,
+    /// Line of the innermost enclosing [`Node::Located`].
+    line: usize,
+}
+
+impl Resolver<'_> {
+    fn expr(&mut self, expr: &Expr) -> Node {
+        let line = self.line_of(expr).filter(|line| *line != self.line);
+        self.located(line, |resolver| resolver.unlocated(expr))
+    }
+
+    fn statement(&mut self, expr: &Expr) -> Node {
+        let line = self.line_of(expr);
+        self.located(line, |resolver| resolver.unlocated(expr))
+    }
+
+    fn line_of(&self, expr: &Expr) -> Option<usize> {
+        self.lines?.line(expr)
+    }
+
+    fn located(&mut self, line: Option<usize>, resolve: impl FnOnce(&mut Self) -> Node) -> Node {
+        let Some(line) = line else {
+            return resolve(self);
+        };
+        let enclosing = std::mem::replace(&mut self.line, line);
+        let node = resolve(self);
+        self.line = enclosing;
+        Node::Located {
+            line,
+            node: Box::new(node),
+        }
+    }
+
+    fn unlocated(&mut self, expr: &Expr) -> Node {
+        match expr {
+            Expr::Number(value) => Node::Const(Value::Number(*value)),
+            Expr::Bool(value) => Node::Const(Value::Bool(*value)),
+            Expr::String(value) => Node::Const(Value::from(value.as_str())),
+            Expr::Symbol(name) => self.var(Symbol::intern(name)),
+            Expr::ListLiteral(items) => Node::ListLiteral(self.exprs(items)),
+            Expr::List(items) => self.list(items),
+            Expr::Call { func, args } => match func.as_ref() {
+                Expr::Symbol(name) => self.application(name, args),
+                other => Node::Call {
+                    func: Box::new(self.expr(other)),
+                    args: self.exprs(args),
+                },
+            },
+        }
+    }
+
+    fn list(&mut self, items: &[Expr]) -> Node {
+        match items.split_first() {
+            None => Node::Const(Value::List(Rc::new([]))),
+            Some((Expr::Symbol(name), args)) => self.application(name, args),
+            Some(_) => Node::Block(self.exprs(items)),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) -> Vec<Node> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    fn application(&mut self, name: &str, args: &[Expr]) -> Node {
+        match name {
+            "nobu" => self.nobu(args),
+            "gakas" => self.gakas(args),
+            "gakasdenu" => self.gakasdenu(args),
+            "import" => import(args),
+            _ => Node::Apply {
+                callee: Box::new(self.var(Symbol::intern(name))),
+                args: self.exprs(args),
+            },
+        }
+    }
+
+    fn nobu(&mut self, args: &[Expr]) -> Node {
+        match args {
+            [condition, then, otherwise] => Node::Nobu(Box::new([
+                self.expr(condition),
+                self.expr(then),
+                self.expr(otherwise),
+            ])),
+            _ => Node::Malformed(Malformed::Arity {
+                form: "nobu",
+                expected: 3,
+                found: args.len(),
+            }),
+        }
+    }
+
+    fn gakas(&mut self, args: &[Expr]) -> Node {
+        if args.len() != 2 {
+            return Node::Malformed(Malformed::Arity {
+                form: "gakas",
+                expected: 2,
+                found: args.len(),
+            });
+        }
+        let Expr::Symbol(name) = &args[0] else {
+            return Node::Malformed(Malformed::Message("gakas: first argument must be symbol"));
+        };
+        let value = self.expr(&args[1]);
+        Node::Gakas {
+            slot: self.define(Symbol::intern(name)),
+            value: Box::new(value),
+        }
+    }
+
+    fn gakasdenu(&mut self, args: &[Expr]) -> Node {
+        if args.len() != 3 {
+            return Node::Malformed(Malformed::Arity {
+                form: "gakasdenu",
+                expected: 3,
+                found: args.len(),
+            });
+        }
+        let Expr::Symbol(name) = &args[0] else {
+            return Node::Malformed(Malformed::Message(
+                "gakasdenu: function name must be symbol",
+            ));
+        };
+        let params = match extract_params(&args[1]) {
+            Ok(params) => params,
+            Err(malformed) => return Node::Malformed(malformed),
+        };
+        let source = extract_body(&args[2]);
+
+        let mut layout: Vec<Symbol> = params.iter().map(|p| Symbol::intern(p)).collect();
+        collect_definitions(&source, &mut layout);
+
+        self.scopes.push(layout);
+        let body = match self.lines {
+            Some(_) => self.body(&args[2]),
+            None => self.exprs(&source),
+        };
+        let layout = self.scopes.pop().unwrap_or_default();
+
+        let name = Symbol::intern(name);
+        Node::Gakasdenu {
+            slot: self.define(name),
+            lambda: Rc::new(Lambda {
+                name,
+                params,
+                source,
+                layout: Rc::new(layout),
+                body,
+                chunk: OnceCell::new(),
+            }),
+        }
+    }
+
+    /// Resolves the statements [`extract_body`] finds in `body` from the
+    /// expressions as written, so that they keep their lines.
+    fn body(&mut self, body: &Expr) -> Vec<Node> {
+        match body {
+            Expr::List(items) if items.iter().all(|item| matches!(item, Expr::List(_))) => {
+                items.iter().map(|item| self.statement(item)).collect()
+            }
+            Expr::List(items) => {
+                let line = items.first().and_then(|first| self.line_of(first));
+                vec![self.located(line, |resolver| resolver.list(items))]
+            }
+            other => vec![self.statement(other)],
+        }
+    }
+
+    fn var(&mut self, name: Symbol) -> Node {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.iter().rposition(|candidate| *candidate == name) {
+                return Node::Var { depth, slot, name };
+            }
+        }
+        Node::Var {
+            depth: self.scopes.len(),
+            slot: self.top.reserve(name),
+            name,
+        }
+    }
+
+    /// Slot in the innermost scope that a definition of `name` writes to.
+    fn define(&mut self, name: Symbol) -> usize {
+        match self.scopes.last() {
+            Some(scope) => scope
+                .iter()
+                .rposition(|candidate| *candidate == name)
+                .expect("definitions are collected before resolving a body"),
+            None => self.top.reserve(name),
+        }
+    }
+}
+
+fn import(args: &[Expr]) -> Node {
+    let (path, alias) = match args {
+        [Expr::String(path)] => (path, None),
+        [Expr::String(path), Expr::Symbol(alias)] => (path, Some(alias.clone())),
+        _ => {
+            return Node::Malformed(Malformed::Message(
+                "import: expected a path string and an optional prefix symbol",
+            ));
+        }
+    };
+    Node::Import(Rc::new(Import {
+        path: path.clone(),
+        alias,
+    }))
+}
+
+fn extract_params(expr: &Expr) -> Result<Vec<String>, Malformed> {
+    match expr {
+        Expr::List(items) => items
+            .iter()
+            .map(|item| match item {
+                Expr::Symbol(name) => Ok(name.clone()),
+                _ => Err(Malformed::Message(
+                    "gakasdenu: parameter list must contain symbols only",
+                )),
+            })
+            .collect(),
+        _ => Err(Malformed::Message(
+            "gakasdenu: second argument must be parameter list",
+        )),
+    }
+}
+
+pub(crate) fn extract_body(expr: &Expr) -> Vec<Expr> {
+    match expr {
+        Expr::List(items) => {
+            if items.iter().all(|item| matches!(item, Expr::List(_))) {
+                items.clone()
+            } else {
+                vec![Expr::List(items.clone())]
+            }
+        }
+        other => vec![other.clone()],
+    }
+}
+
+/// Adds every name bound by `gakas`/`gakasdenu` in `exprs` to `layout`,
+/// without descending into nested function bodies.
+pub(crate) fn collect_definitions(exprs: &[Expr], layout: &mut Vec<Symbol>) {
+    for expr in exprs {
+        let (head, args) = match expr {
+            Expr::List(items) => match items.split_first() {
+                Some((head, args)) => (head, args),
+                None => continue,
+            },
+            Expr::Call { func, args } => (func.as_ref(), args.as_slice()),
+            Expr::ListLiteral(items) => {
+                collect_definitions(items, layout);
+                continue;
+            }
+            _ => continue,
+        };
+        match (head.as_symbol(), args) {
+            (Some("gakas"), [Expr::Symbol(name), value]) => {
+                push_unique(layout, Symbol::intern(name));
+                collect_definitions(std::slice::from_ref(value), layout);
+            }
+            (Some("gakasdenu"), [Expr::Symbol(name), _, _]) => {
+                push_unique(layout, Symbol::intern(name));
+            }
+            (Some(_), _) => collect_definitions(args, layout),
+            (None, _) => {
+                collect_definitions(std::slice::from_ref(head), layout);
+                collect_definitions(args, layout);
+            }
+        }
+    }
+}
+
+fn push_unique(layout: &mut Vec<Symbol>, name: Symbol) {
+    if !layout.contains(&name) {
+        layout.push(name);
+    }
+}
@@ -0,0 +1,85 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+
+/// An interned identifier. Each name is stored once for the rest of the
+/// process, so comparing and hashing a `Symbol` only looks at the address of
+/// that copy, and reading the name back takes no lock.
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+fn interner() -> &'static Mutex<HashSet<&'static str>> {
+    static INTERNER: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    INTERNER.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        let mut names = interner().lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(name) = names.get(name) {
+            return Symbol(name);
+        }
+        // Interned names live for the rest of the process, so leaking them
+        // lets a `Symbol` hold a plain `&'static str`.
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        names.insert(name);
+        Symbol(name)
+    }
+
+    /// The symbol for `name` if it has been interned, without interning it.
+    /// Lookups of names that were never defined leave nothing behind.
+    pub fn lookup(name: &str) -> Option<Self> {
+        let names = interner().lock().unwrap_or_else(|err| err.into_inner());
+        names.get(name).map(|name| Symbol(name))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state);
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({})", self.as_str())
+    }
+}
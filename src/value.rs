@@ -2,9 +2,9 @@ use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
-use crate::{ast::Expr, env::Environment, error::SatukitanError};
+use crate::{env::Environment, error::SatukitanError, resolver::Lambda, symbol::Symbol};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Arity {
     Exact(usize),
     AtLeast(usize),
@@ -15,8 +15,8 @@ pub enum Arity {
 pub enum Value {
     Number(i64),
     Bool(bool),
-    String(String),
-    List(Vec<Value>),
+    String(Rc<str>),
+    List(Rc<[Value]>),
     Function(Rc<FunctionValue>),
     Builtin(BuiltinFunction),
     Nil,
@@ -24,39 +24,140 @@ pub enum Value {
 
 #[derive(Clone)]
 pub struct FunctionValue {
-    pub params: Vec<String>,
-    pub body: Vec<Expr>,
+    pub lambda: Rc<Lambda>,
     pub env: Rc<RefCell<Environment>>,
 }
 
-#[derive(Clone, Copy)]
+/// Host function backing a builtin.
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, SatukitanError>;
+
+#[derive(Clone)]
 pub struct BuiltinFunction {
     pub name: &'static str,
-    func: fn(&[Value]) -> Result<Value, SatukitanError>,
+    /// Kept behind one `Rc` so that builtin values stay small and cheap to
+    /// clone.
+    info: Rc<BuiltinInfo>,
+}
+
+#[derive(Clone)]
+struct BuiltinInfo {
+    func: Rc<NativeFn>,
     arity: Arity,
+    doc: Option<Rc<str>>,
+    annotation: Option<Rc<str>>,
+    pure: bool,
+}
+
+impl Arity {
+    /// Number of arguments a builtin needs before it can be applied.
+    pub fn min(self) -> usize {
+        match self {
+            Arity::Exact(n) | Arity::AtLeast(n) => n,
+            Arity::Any => 0,
+        }
+    }
+
+    /// Rejects an argument count that `self` does not accept.
+    pub fn check(self, name: &str, found: usize) -> Result<(), SatukitanError> {
+        match self {
+            Arity::Exact(n) if found != n => Err(SatukitanError::arity_exact(name, n, found)),
+            Arity::AtLeast(n) if found < n => Err(SatukitanError::arity_at_least(name, n, found)),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl FunctionValue {
-    pub fn new(params: Vec<String>, body: Vec<Expr>, env: Rc<RefCell<Environment>>) -> Self {
-        Self { params, body, env }
+    pub fn new(lambda: Rc<Lambda>, env: Rc<RefCell<Environment>>) -> Self {
+        Self { lambda, env }
+    }
+
+    pub fn params(&self) -> &[String] {
+        &self.lambda.params
+    }
+
+    /// Binds `args` to the parameters in a fresh frame for one call.
+    pub fn call_frame(&self, args: Vec<Value>) -> Result<Rc<RefCell<Environment>>, SatukitanError> {
+        let lambda = &self.lambda;
+        if lambda.arity() != args.len() {
+            return Err(SatukitanError::arity_exact(
+                "lambda".to_string(),
+                lambda.arity(),
+                args.len(),
+            ));
+        }
+
+        // Collecting in place reuses the allocation of `args` for the frame.
+        let mut slots: Vec<Option<Value>> = args.into_iter().map(Some).collect();
+        slots.resize(lambda.layout.len(), None);
+
+        Ok(Rc::new(RefCell::new(Environment::with_layout(
+            self.env.clone(),
+            lambda.layout.clone(),
+            slots,
+        ))))
     }
 }
 
 impl BuiltinFunction {
+    /// Wraps `func` as a builtin. The closure may capture host state; the
+    /// argument count is checked against `arity` before it is called.
     pub fn new(
-        name: &'static str,
+        name: impl AsRef<str>,
         arity: Arity,
-        func: fn(&[Value]) -> Result<Value, SatukitanError>,
+        func: impl Fn(&[Value]) -> Result<Value, SatukitanError> + 'static,
     ) -> Self {
-        Self { name, func, arity }
+        Self {
+            name: Symbol::intern(name.as_ref()).as_str(),
+            info: Rc::new(BuiltinInfo {
+                func: Rc::new(func),
+                arity,
+                doc: None,
+                annotation: None,
+                pure: false,
+            }),
+        }
+    }
+
+    /// Attaches a human readable description.
+    pub fn with_doc(mut self, doc: impl Into<Rc<str>>) -> Self {
+        Rc::make_mut(&mut self.info).doc = Some(doc.into());
+        self
+    }
+
+    /// Attaches the signature shown next to the name in REPL completions,
+    /// e.g. `(num num -> num)`.
+    pub fn with_annotation(mut self, annotation: impl Into<Rc<str>>) -> Self {
+        Rc::make_mut(&mut self.info).annotation = Some(annotation.into());
+        self
+    }
+
+    /// Marks the builtin as free of side effects, allowing the optimiser to
+    /// evaluate it ahead of time.
+    pub(crate) fn into_pure(mut self) -> Self {
+        Rc::make_mut(&mut self.info).pure = true;
+        self
+    }
+
+    pub fn is_pure(&self) -> bool {
+        self.info.pure
     }
 
     pub fn call(&self, args: &[Value]) -> Result<Value, SatukitanError> {
-        (self.func)(args)
+        self.info.arity.check(self.name, args.len())?;
+        (self.info.func)(args)
     }
 
     pub fn arity(&self) -> Arity {
-        self.arity
+        self.info.arity
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.info.doc.as_deref()
+    }
+
+    pub fn annotation(&self) -> Option<&str> {
+        self.info.annotation.as_deref()
     }
 }
 
@@ -93,7 +194,7 @@ impl Value {
 
     pub fn into_list(self) -> Result<Vec<Value>, SatukitanError> {
         match self {
-            Value::List(items) => Ok(items),
+            Value::List(items) => Ok(items.to_vec()),
             other => Err(SatukitanError::type_mismatch("list", other.type_name())),
         }
     }
@@ -105,7 +206,10 @@ impl Value {
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Nil, Value::Nil) => true,
             (Value::List(a), Value::List(b)) => {
-                a.len() == b.len() && a.iter().zip(b).all(|(lhs, rhs)| lhs.structural_eq(rhs))
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(lhs, rhs)| lhs.structural_eq(rhs))
             }
             (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
             (Value::Builtin(a), Value::Builtin(b)) => a.name == b.name,
@@ -114,6 +218,42 @@ impl Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Value::List(items.into())
+    }
+}
+
+impl From<BuiltinFunction> for Value {
+    fn from(builtin: BuiltinFunction) -> Self {
+        Value::Builtin(builtin)
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -133,7 +273,7 @@ impl fmt::Display for Value {
             }
             Value::Function(func) => {
                 write!(f, "<lambda (")?;
-                for (idx, param) in func.params.iter().enumerate() {
+                for (idx, param) in func.params().iter().enumerate() {
                     if idx > 0 {
                         write!(f, " ")?;
                     }
@@ -152,7 +292,7 @@ impl fmt::Debug for Value {
         match self {
             Value::Function(func) => f
                 .debug_tuple("Function")
-                .field(&format!("params={:?}", func.params))
+                .field(&format!("params={:?}", func.params()))
                 .finish(),
             Value::Builtin(builtin) => f.debug_tuple("Builtin").field(&builtin.name).finish(),
             _ => fmt::Display::fmt(self, f),
@@ -163,8 +303,8 @@ impl fmt::Debug for Value {
 impl fmt::Debug for FunctionValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FunctionValue")
-            .field("params", &self.params)
-            .field("body_len", &self.body.len())
+            .field("params", &self.lambda.params)
+            .field("body_len", &self.lambda.body.len())
             .finish()
     }
 }
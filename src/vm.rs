@@ -0,0 +1,244 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::compiler::{Chunk, Op, compile_program, lambda_chunk};
+use crate::env::Environment;
+use crate::error::SatukitanError;
+use crate::limits::Budget;
+use crate::module;
+use crate::value::{BuiltinFunction, FunctionValue, Value};
+
+/// Compiles `program` to bytecode and runs it in `env`.
+pub fn run_program(
+    program: &[Expr],
+    env: Rc<RefCell<Environment>>,
+    budget: &mut Budget,
+) -> Result<Value, SatukitanError> {
+    let chunk = compile_program(program, &mut env.borrow_mut());
+    Vm::new(budget).run(Rc::new(chunk), env)
+}
+
+struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    env: Rc<RefCell<Environment>>,
+}
+
+/// An argument list under construction. `base` is the stack index of the
+/// first argument; the callee sits just below it.
+struct ArgList {
+    base: usize,
+    collecting: Option<Collecting>,
+}
+
+/// A builtin argument that is consuming the arguments following it.
+struct Collecting {
+    builtin: BuiltinFunction,
+    start: usize,
+    left: usize,
+}
+
+pub struct Vm<'a> {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    args: Vec<ArgList>,
+    budget: &'a mut Budget,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(budget: &'a mut Budget) -> Self {
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            args: Vec::new(),
+            budget,
+        }
+    }
+
+    pub fn run(
+        &mut self,
+        chunk: Rc<Chunk>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Value, SatukitanError> {
+        self.frames.push(Frame { chunk, ip: 0, env });
+        let result = self.execute();
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    /// Calls a function or builtin value with already evaluated arguments.
+    pub fn apply(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, SatukitanError> {
+        let base = self.stack.len() + 1;
+        self.stack.push(callee);
+        self.stack.extend(args);
+        let result = self.call(base).and_then(|()| {
+            if self.frames.is_empty() {
+                Ok(self.pop())
+            } else {
+                self.execute()
+            }
+        });
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    fn execute(&mut self) -> Result<Value, SatukitanError> {
+        loop {
+            self.budget.step()?;
+            let frame = self.frames.last_mut().expect("vm has an active frame");
+            let op = frame.chunk.code[frame.ip];
+            frame.ip += 1;
+
+            match op {
+                Op::Const(idx) => {
+                    let value = &frame.chunk.constants[idx as usize];
+                    self.budget.check_value(value)?;
+                    self.stack.push(value.clone());
+                }
+                Op::Nil => self.stack.push(Value::Nil),
+                Op::Load { depth, slot, name } => {
+                    let value = frame
+                        .env
+                        .borrow()
+                        .load(depth as usize, slot as usize, name)
+                        .ok_or_else(|| SatukitanError::UndefinedSymbol(name.to_string()))?;
+                    self.stack.push(value);
+                }
+                Op::Store(slot) => {
+                    let value = self.stack.last().expect("vm stack underflow").clone();
+                    frame.env.borrow_mut().store(slot as usize, value);
+                }
+                Op::Pop => {
+                    self.stack.pop();
+                }
+                Op::List(len) => {
+                    let items = self.stack.split_off(self.stack.len() - len as usize);
+                    let list = Value::from(items);
+                    self.budget.check_value(&list)?;
+                    self.stack.push(list);
+                }
+                Op::Closure(idx) => {
+                    let lambda = frame.chunk.lambdas[idx as usize].clone();
+                    let function = FunctionValue::new(lambda, frame.env.clone());
+                    self.stack.push(Value::Function(Rc::new(function)));
+                }
+                Op::Branch(target) => match self.stack.pop().expect("vm stack underflow") {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => frame.ip = target as usize,
+                    other => {
+                        return Err(SatukitanError::type_mismatch("boolean", other.type_name()));
+                    }
+                },
+                Op::Jump(target) => frame.ip = target as usize,
+                Op::BeginArgs => self.args.push(ArgList {
+                    base: self.stack.len(),
+                    collecting: None,
+                }),
+                Op::Arg { remaining } => self.argument(remaining as usize)?,
+                Op::Call => {
+                    let list = self.args.pop().expect("call follows an argument list");
+                    self.call(list.base)?;
+                }
+                Op::Apply => {
+                    if let Some(Value::Function(_) | Value::Builtin(_)) = self.stack.last() {
+                        self.call(self.stack.len())?;
+                    }
+                }
+                Op::Import(idx) => {
+                    let import = frame.chunk.imports[idx as usize].clone();
+                    let env = frame.env.clone();
+                    let budget = &mut *self.budget;
+                    let result = module::import(&import, &env, |program, module_env| {
+                        run_program(program, module_env, budget)
+                    })?;
+                    self.stack.push(result);
+                }
+                Op::Fail(idx) => return Err(frame.chunk.failures[idx as usize].to_error()),
+                Op::Return => {
+                    self.frames.pop();
+                    if !self.frames.is_empty() {
+                        self.budget.leave_call();
+                    }
+                    if self.frames.is_empty() {
+                        return Ok(self.pop());
+                    }
+                }
+            }
+        }
+    }
+
+    fn argument(&mut self, remaining: usize) -> Result<(), SatukitanError> {
+        let list = self
+            .args
+            .last_mut()
+            .expect("argument inside an argument list");
+        if let Some(collecting) = &mut list.collecting {
+            collecting.left -= 1;
+            if collecting.left == 0 {
+                let result = collecting.builtin.call(&self.stack[collecting.start..])?;
+                self.budget.check_value(&result)?;
+                self.stack.truncate(collecting.start);
+                list.collecting = None;
+                self.stack.push(result);
+            }
+        } else if let Some(Value::Builtin(builtin)) = self.stack.last() {
+            let required = builtin.arity().min();
+            if required > 0 && remaining >= required {
+                let builtin = builtin.clone();
+                self.stack.pop();
+                list.collecting = Some(Collecting {
+                    builtin,
+                    start: self.stack.len(),
+                    left: required,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the callee at stack index `base - 1` with the arguments above
+    /// it. A builtin's result replaces the callee and its arguments; a
+    /// function takes them into a new frame.
+    fn call(&mut self, base: usize) -> Result<(), SatukitanError> {
+        match self.stack[base - 1].clone() {
+            Value::Builtin(builtin) => {
+                let result = builtin.call(&self.stack[base..])?;
+                self.budget.check_value(&result)?;
+                self.stack.truncate(base - 1);
+                self.stack.push(result);
+                Ok(())
+            }
+            Value::Function(function) => {
+                let args = self.stack.split_off(base);
+                self.stack.pop();
+                let env = function.call_frame(args)?;
+                self.budget.enter_call()?;
+                self.frames.push(Frame {
+                    chunk: lambda_chunk(&function.lambda),
+                    ip: 0,
+                    env,
+                });
+                Ok(())
+            }
+            other => Err(SatukitanError::Eval(format!(
+                "attempted to call non-callable value of type {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.args.clear();
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("vm stack underflow")
+    }
+}
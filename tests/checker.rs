@@ -0,0 +1,146 @@
+use std::fs;
+use std::process::Command;
+
+use satukitan_rs::Interpreter;
+use satukitan_rs::checker::check_program;
+use satukitan_rs::cst::SyntaxTree;
+use satukitan_rs::interpreter::PRELUDE;
+
+/// The diagnostics for `source` as `line:column: severity: message`.
+fn check(source: &str) -> Vec<String> {
+    let tree = SyntaxTree::parse(source).unwrap();
+    let program = tree.to_program().unwrap();
+    let globals = Interpreter::new().environment();
+    check_program(&program, &globals.borrow())
+        .into_iter()
+        .map(|diagnostic| {
+            let (line, column) = diagnostic.span(&tree).unwrap().position(source);
+            format!(
+                "{}:{}: {}: {}",
+                line + 1,
+                column + 1,
+                diagnostic.severity,
+                diagnostic.message
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn clean_programs_have_no_diagnostics() {
+    assert!(check(include_str!("../examples/fibonacci.st")).is_empty());
+    assert!(check(include_str!("../examples/hello.st")).is_empty());
+    let globals = Interpreter::bare().environment();
+    let prelude = satukitan_rs::parser::parse_program(PRELUDE).unwrap();
+    assert!(check_program(&prelude, &globals.borrow()).is_empty());
+}
+
+#[test]
+fn undefined_names_are_errors() {
+    assert_eq!(
+        check("gakas x ra\nsipus (ritas x y)"),
+        ["2:16: error: undefined symbol: y"]
+    );
+    // Functions may refer to names defined after them.
+    assert!(check("gakasdenu f () (g)\ngakasdenu g () (ru)\nf").is_empty());
+    // Imported names are only known by their prefix.
+    assert!(check("import \"lib/math\"\nimport \"util\" u\nmath.square (u.double ra)").is_empty());
+}
+
+#[test]
+fn argument_counts_are_checked() {
+    let source = "\
+gakasdenu add (a b) (ritas a b)
+add ru
+sipus matyes ra ru
+head [ru] [ra]
+(max ru)";
+    assert_eq!(
+        check(source),
+        [
+            "2:1: error: arity mismatch in add: expected 2, found 1",
+            "4:1: error: arity mismatch in head: expected 1, found 2",
+            "5:2: error: arity mismatch in max: expected 2, found 1",
+        ]
+    );
+}
+
+#[test]
+fn malformed_special_forms_are_errors() {
+    assert_eq!(
+        check("nobu me (ru)\ngakas x\ngakasdenu f x (x)"),
+        [
+            "1:1: error: arity mismatch in nobu: expected 3, found 2",
+            "2:1: error: arity mismatch in gakas: expected 2, found 1",
+            "3:1: error: evaluation error: gakasdenu: second argument must be parameter list",
+        ]
+    );
+}
+
+#[test]
+fn unused_bindings_are_warnings() {
+    let source = "\
+gakasdenu f (x _ignored) (
+    (gakas local ru)
+    (gakas kept ra)
+    (ritas kept ru)
+)
+gakas exported ru
+gakas _private ra";
+    assert_eq!(
+        check(source),
+        [
+            "1:14: warning: unused parameter 'x'",
+            "2:12: warning: unused binding 'local'",
+            "7:7: warning: unused binding '_private'",
+        ]
+    );
+}
+
+#[test]
+fn shadowing_builtins_is_a_warning() {
+    assert_eq!(
+        check("gakas ritas ru\ngakasdenu f (sipus) (sipus)\nf ra"),
+        [
+            "1:7: warning: 'ritas' shadows a builtin",
+            "2:14: warning: 'sipus' shadows a builtin",
+        ]
+    );
+}
+
+#[test]
+fn diagnostics_point_at_the_right_occurrence() {
+    let source = "gakasdenu add (a b) (ritas a b)\nsipus [(add ru ra) (add ru)]";
+    let diagnostics = check(source);
+    assert_eq!(
+        diagnostics,
+        ["2:21: error: arity mismatch in add: expected 2, found 1"]
+    );
+}
+
+#[test]
+fn check_subcommand_reports_and_fails_on_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let clean = dir.path().join("clean.st");
+    fs::write(&clean, "gakas _unused ru\n").unwrap();
+    let broken = dir.path().join("broken.st");
+    fs::write(&broken, "sipus nowhere\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("check")
+        .arg(&clean)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("clean.st:1:7: warning: unused binding '_unused'\n"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("check")
+        .arg(&broken)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("broken.st:1:7: error: undefined symbol: nowhere\n"));
+}
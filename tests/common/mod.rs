@@ -0,0 +1,97 @@
+//! Helpers shared by the integration tests.
+//!
+//! Programs evaluated through [`run`], [`run_with`] and [`eval`] are run on
+//! both backends, with and without the optimizer, and under a debugger that
+//! steps through every line. All of them must agree, so every test using
+//! these helpers is also a differential test. Tests that keep one
+//! interpreter across several evaluations loop over [`BACKENDS`] instead.
+#![allow(dead_code)]
+
+use satukitan_rs::debugger::{Command, Debugger, Pause};
+use satukitan_rs::io::OutputBuffer;
+use satukitan_rs::value::Value;
+use satukitan_rs::{Backend, Interpreter, SatukitanError};
+
+pub const BACKENDS: [Backend; 2] = [Backend::TreeWalk, Backend::Bytecode];
+
+/// One way of evaluating a program.
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    Plain(Backend),
+    Optimized(Backend),
+    Debugged,
+}
+
+const MODES: [Mode; 5] = [
+    Mode::Plain(Backend::TreeWalk),
+    Mode::Plain(Backend::Bytecode),
+    Mode::Optimized(Backend::TreeWalk),
+    Mode::Optimized(Backend::Bytecode),
+    Mode::Debugged,
+];
+
+/// The result of a program and what it printed.
+pub struct Run {
+    pub result: Result<Value, SatukitanError>,
+    pub output: String,
+}
+
+impl Run {
+    fn summary(&self) -> String {
+        let result = match &self.result {
+            Ok(value) => format!("ok: {value}"),
+            Err(err) => format!("error: {err}"),
+        };
+        format!("{result}\noutput: {:?}", self.output)
+    }
+}
+
+/// Evaluates `source` in a fresh interpreter in every mode and returns the
+/// run on the tree-walking backend, after checking the other modes agree.
+pub fn run(source: &str) -> Run {
+    run_with(|_| {}, source)
+}
+
+/// Like [`run`], but prepares each fresh interpreter with `setup` first.
+pub fn run_with(setup: impl Fn(&mut Interpreter), source: &str) -> Run {
+    let mut first: Option<Run> = None;
+    for mode in MODES {
+        let backend = match mode {
+            Mode::Plain(backend) | Mode::Optimized(backend) => backend,
+            Mode::Debugged => Backend::TreeWalk,
+        };
+        let mut interpreter = Interpreter::with_backend(backend);
+        let output = OutputBuffer::new();
+        interpreter.set_output(output.clone());
+        setup(&mut interpreter);
+        let result = match mode {
+            Mode::Plain(_) => interpreter.eval_str(source),
+            Mode::Optimized(_) => {
+                interpreter.set_optimize(true);
+                interpreter.eval_str(source)
+            }
+            Mode::Debugged => {
+                let debugger = Debugger::new(|_: &mut Pause| Ok(Command::StepIn));
+                interpreter.debug_str(source, debugger)
+            }
+        };
+        let run = Run {
+            result,
+            output: output.take(),
+        };
+        match &first {
+            Some(expected) => assert_eq!(
+                run.summary(),
+                expected.summary(),
+                "{mode:?} disagrees on {source:?}"
+            ),
+            None => first = Some(run),
+        }
+    }
+    first.expect("at least one mode")
+}
+
+/// The value of `source`, checked in every mode like [`run`].
+pub fn eval(source: &str) -> Result<Value, SatukitanError> {
+    run(source).result
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+mod common;
+
+use common::BACKENDS;
+use satukitan_rs::convert::{FromValue, IntoValue};
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+fn eval<T: FromValue>(source: &str) -> Result<T, SatukitanError> {
+    T::from_value(common::eval(source)?)
+}
+
+#[test]
+fn scalars_round_trip() {
+    assert_eq!(eval::<i64>("ritas ra ro").unwrap(), 5);
+    assert!(eval::<bool>("ditas ru ra").unwrap());
+    assert_eq!(eval::<String>(r#""sipus""#).unwrap(), "sipus");
+    assert_eq!(i64::from_value(7.into_value()).unwrap(), 7);
+    assert_eq!(String::from_value("a".into_value()).unwrap(), "a");
+}
+
+#[test]
+fn option_maps_nil_to_none() {
+    assert_eq!(Option::<i64>::from_value(Value::Nil).unwrap(), None);
+    assert_eq!(eval::<Option<i64>>("ru").unwrap(), Some(1));
+    assert!(matches!(None::<i64>.into_value(), Value::Nil));
+}
+
+#[test]
+fn collections_convert_element_wise() {
+    assert_eq!(
+        eval::<Vec<Vec<i64>>>("[[ru ra] [ro]]").unwrap(),
+        vec![vec![1, 2], vec![3]]
+    );
+    assert_eq!(
+        eval::<(i64, bool, String)>(r#"[ru me "x"]"#).unwrap(),
+        (1, true, "x".to_string())
+    );
+
+    let map = eval::<HashMap<String, i64>>(r#"[["a" ru] ["b" ra]]"#).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["b"], 2);
+    let back = HashMap::<String, i64>::from_value(map.clone().into_value()).unwrap();
+    assert_eq!(back, map);
+}
+
+#[test]
+fn mismatches_report_expected_type() {
+    assert!(matches!(
+        eval::<i64>("me"),
+        Err(SatukitanError::TypeMismatch { ref expected, ref found })
+            if expected == "number" && found == "boolean"
+    ));
+    assert!(matches!(
+        eval::<Vec<i64>>("[ru me]"),
+        Err(SatukitanError::TypeMismatch { ref found, .. }) if found == "boolean"
+    ));
+    assert!(matches!(
+        eval::<(i64, i64)>("[ru ra ro]"),
+        Err(SatukitanError::TypeMismatch { ref found, .. }) if found == "list of length 3"
+    ));
+}
+
+fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[test]
+fn typed_host_functions_are_adapted() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter.register_typed("tasu", add);
+        interpreter.register_typed("hanten", |items: Vec<Value>| {
+            items.into_iter().rev().collect::<Vec<_>>()
+        });
+        interpreter.register_typed("wari", |a: i64, b: i64| {
+            if b == 0 {
+                Err(SatukitanError::Eval("division by zero".into()))
+            } else {
+                Ok(a / b)
+            }
+        });
+
+        let sum = i64::from_value(interpreter.eval_str("(tasu ra ro)").unwrap()).unwrap();
+        assert_eq!(sum, 5);
+        let reversed = interpreter.eval_str("hanten [ru ra]").unwrap();
+        assert_eq!(Vec::<i64>::from_value(reversed).unwrap(), vec![2, 1]);
+        assert!(matches!(
+            interpreter.eval_str("(wari ru rv)"),
+            Err(SatukitanError::Eval(_))
+        ));
+        assert!(matches!(
+            interpreter.eval_str(r#"(tasu ru "ra")"#),
+            Err(SatukitanError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            interpreter.eval_str("(tasu ru)"),
+            Err(SatukitanError::ArityMismatch { .. })
+        ));
+    }
+}
+
+#[test]
+fn call_typed_converts_arguments_and_result() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter
+            .eval_str("(gakasdenu hiku (a b) (matyes a b))")
+            .unwrap();
+        let difference: i64 = interpreter.call_typed("hiku", (5i64, 2i64)).unwrap();
+        assert_eq!(difference, 3);
+        let sorted: Vec<i64> = interpreter
+            .call_typed("fanitas", (vec![3i64, 1, 2],))
+            .unwrap();
+        assert_eq!(sorted, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,139 @@
+use satukitan_rs::ast::Expr;
+use satukitan_rs::cst::{Bracket, NodeKind, Span, SyntaxTree, Trivia};
+use satukitan_rs::interpreter::PRELUDE;
+use satukitan_rs::parser::parse_program;
+
+const SOURCES: &[&str] = &[
+    "",
+    "ritas ra ru",
+    "# only a comment",
+    "gakas x   ra   # trailing\n\n\nx\n",
+    "gakasdenu f (a b) ( # after open\n  nobu (fityes a b) # cond\n     (a)\n  # own line\n     (b) )\n",
+    "[ ru\n ra ]  (ritas\tra ru)\r\nsipus \"a \\\" # b\"\n   ",
+    PRELUDE,
+    include_str!("../examples/fibonacci.st"),
+];
+
+#[test]
+fn printing_the_tree_gives_back_the_source() {
+    for source in SOURCES {
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), *source);
+    }
+}
+
+#[test]
+fn the_derived_program_matches_the_parser() {
+    for source in SOURCES {
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_program().unwrap(), parse_program(source).unwrap());
+    }
+}
+
+#[test]
+fn comments_are_attached_to_the_code_they_annotate() {
+    let tree = SyntaxTree::parse("# doc\ngakas x ra # note\nx").unwrap();
+    assert_eq!(tree.nodes.len(), 2);
+    assert_eq!(
+        tree.nodes[0].leading,
+        [
+            Trivia::Comment("# doc".to_string()),
+            Trivia::Whitespace("\n".to_string())
+        ]
+    );
+    assert_eq!(
+        tree.nodes[0].trailing,
+        [
+            Trivia::Whitespace(" ".to_string()),
+            Trivia::Comment("# note".to_string()),
+        ]
+    );
+    assert_eq!(
+        tree.nodes[1].leading,
+        [Trivia::Whitespace("\n".to_string())]
+    );
+    assert!(tree.nodes[1].trailing.is_empty());
+
+    let tree = SyntaxTree::parse("(ru # one\n  ra\n  # own line\n  ro)").unwrap();
+    let NodeKind::Group { children, .. } = &tree.nodes[0].kind else {
+        panic!("expected a group");
+    };
+    assert_eq!(
+        children[0].trailing[1],
+        Trivia::Comment("# one".to_string())
+    );
+    assert!(children[1].trailing.is_empty());
+    assert_eq!(
+        children[2].leading[1],
+        Trivia::Comment("# own line".to_string())
+    );
+}
+
+#[test]
+fn removing_a_node_takes_its_trailing_comment_along() {
+    let mut tree = SyntaxTree::parse("gakas x ra # two\ngakas y ro\n").unwrap();
+    tree.nodes.remove(0);
+    assert_eq!(tree.to_string(), "\ngakas y ro\n");
+}
+
+#[test]
+fn calls_take_arguments_on_their_own_line() {
+    let tree = SyntaxTree::parse("sipus (ritas ra ru) \"s\"\nru").unwrap();
+    let NodeKind::Call { head, args } = &tree.nodes[0].kind else {
+        panic!("expected a call, got {:?}", tree.nodes[0].kind);
+    };
+    assert_eq!(head.identifier(), Some("sipus"));
+    assert_eq!(args.len(), 2);
+    assert!(matches!(
+        args[0].kind,
+        NodeKind::Group {
+            bracket: Bracket::Paren,
+            ..
+        }
+    ));
+    assert_eq!(tree.nodes[0].span, Span { start: 0, end: 23 });
+    assert_eq!(tree.nodes[1].span, Span { start: 24, end: 26 });
+}
+
+#[test]
+fn rewriting_a_node_keeps_the_comments() {
+    let mut tree = SyntaxTree::parse("# answer\ngakas x ra # two\n").unwrap();
+    let NodeKind::Call { args, .. } = &mut tree.nodes[0].kind else {
+        panic!("expected a call");
+    };
+    args[1].kind = NodeKind::Identifier("ro".to_string());
+    assert_eq!(tree.to_string(), "# answer\ngakas x ro # two\n");
+}
+
+#[test]
+fn comment_markers_inside_strings_are_kept() {
+    let program = parse_program("sipus \"a # b\"").unwrap();
+    assert_eq!(
+        program,
+        [Expr::Call {
+            func: Box::new(Expr::Symbol("sipus".to_string())),
+            args: vec![Expr::String("a # b".to_string())],
+        }]
+    );
+}
+
+#[test]
+fn malformed_sources_report_where_they_fail() {
+    let err = SyntaxTree::parse("sipus (ritas ra]").unwrap_err();
+    assert_eq!(err.to_string(), "parse error: unexpected token near '…]'");
+    let err = SyntaxTree::parse("(a(b))").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parse error: unexpected token near '…(b))'"
+    );
+    let err = SyntaxTree::parse("sipus \"open").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parse error: unexpected token near '…\"open'"
+    );
+    let err = parse_program("sipus \"\\q\"").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parse error: invalid escape sequence in \"\\q\""
+    );
+}
@@ -0,0 +1,264 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::process::{Command as Process, Stdio};
+use std::rc::Rc;
+
+use satukitan_rs::debugger::{Command, Console, Debugger, Pause, Reason};
+use satukitan_rs::io::{Input, OutputBuffer};
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+const PROGRAM: &str = "\
+gakasdenu square (x) (
+    nitas x x
+)
+gakasdenu sum-squares (a b) (
+    (gakas total (ritas (square a) (square b)))
+    (total)
+)
+sipus (sum-squares ru ra)
+ro
+";
+
+/// Runs `source` under a debugger that answers its pauses with `commands`
+/// and then continues. Returns the pauses as `line reason frames`.
+fn trace(debugger: impl FnOnce(Debugger) -> Debugger, commands: &[Command]) -> Vec<String> {
+    let pauses = Rc::new(RefCell::new(Vec::new()));
+    let recorded = pauses.clone();
+    let mut commands = VecDeque::from(commands.to_vec());
+    let frontend = move |pause: &mut Pause| {
+        let frames = pause
+            .frames
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect::<Vec<_>>();
+        recorded.borrow_mut().push(
+            format!("{} {:?} {}", pause.line, pause.reason, frames.join(" "))
+                .trim_end()
+                .to_string(),
+        );
+        Ok(commands.pop_front().unwrap_or(Command::Continue))
+    };
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(OutputBuffer::new());
+    let result = interpreter
+        .debug_str(PROGRAM, debugger(Debugger::new(frontend)))
+        .unwrap();
+    assert!(matches!(result, Value::Number(3)));
+    pauses.take()
+}
+
+#[test]
+fn step_in_pauses_at_every_line_including_calls() {
+    let pauses = trace(|debugger| debugger, &[Command::StepIn; 8]);
+    assert_eq!(
+        pauses,
+        [
+            "1 Entry",
+            "4 Step",
+            "8 Step",
+            "5 Step sum-squares",
+            "2 Step sum-squares square",
+            "2 Step sum-squares square",
+            "6 Step sum-squares",
+            "9 Step",
+        ]
+    );
+}
+
+#[test]
+fn step_over_and_out_skip_calls() {
+    let pauses = trace(
+        |debugger| debugger,
+        &[Command::StepOver, Command::StepOver, Command::StepOver],
+    );
+    assert_eq!(pauses, ["1 Entry", "4 Step", "8 Step", "9 Step"]);
+
+    let pauses = trace(
+        |debugger| debugger,
+        &[
+            Command::StepIn,
+            Command::StepIn,
+            Command::StepIn,
+            Command::StepOver,
+            Command::StepIn,
+            Command::StepOut,
+        ],
+    );
+    assert_eq!(
+        pauses,
+        [
+            "1 Entry",
+            "4 Step",
+            "8 Step",
+            "5 Step sum-squares",
+            "6 Step sum-squares",
+            "9 Step",
+        ]
+    );
+}
+
+#[test]
+fn breakpoints_pause_every_time_their_line_runs() {
+    let pauses = trace(
+        |mut debugger| {
+            debugger.set_stop_on_entry(false);
+            debugger.add_breakpoint(2);
+            debugger
+        },
+        &[],
+    );
+    assert_eq!(
+        pauses,
+        [
+            "2 Breakpoint sum-squares square",
+            "2 Breakpoint sum-squares square"
+        ]
+    );
+
+    // Steps stop at breakpoints they run into.
+    let pauses = trace(
+        |mut debugger| {
+            debugger.add_breakpoint(2);
+            debugger
+        },
+        &[Command::StepOver, Command::StepOver, Command::StepOver],
+    );
+    assert_eq!(
+        pauses,
+        [
+            "1 Entry",
+            "4 Step",
+            "8 Step",
+            "2 Breakpoint sum-squares square",
+            "2 Breakpoint sum-squares square",
+        ]
+    );
+}
+
+#[test]
+fn pauses_expose_the_environment_chain() {
+    let scopes = Rc::new(RefCell::new(Vec::new()));
+    let seen = scopes.clone();
+    let mut debugger = Debugger::new(move |pause: &mut Pause| {
+        *seen.borrow_mut() = pause
+            .scopes()
+            .into_iter()
+            .map(|bindings| {
+                bindings
+                    .into_iter()
+                    .filter(|(_, value)| !matches!(value, Value::Builtin(_)))
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        Ok(Command::Continue)
+    });
+    debugger.set_stop_on_entry(false);
+    debugger.add_breakpoint(6);
+    let mut interpreter = Interpreter::bare();
+    interpreter.set_output(OutputBuffer::new());
+    interpreter.debug_str(PROGRAM, debugger).unwrap();
+    assert_eq!(
+        *scopes.borrow(),
+        [
+            "a=ru b=ra total=ri",
+            "args=[] square=<lambda (x)> sum-squares=<lambda (a b)>",
+        ]
+    );
+}
+
+#[test]
+fn front_ends_can_set_breakpoints_and_stop_the_program() {
+    let mut debugger = Debugger::new(|pause: &mut Pause| match pause.reason {
+        Reason::Entry => {
+            pause.breakpoints.insert(5);
+            Ok(Command::Continue)
+        }
+        _ => Err(SatukitanError::Eval(format!("stopped at {}", pause.line))),
+    });
+    debugger.add_breakpoint(9);
+    let err = Interpreter::new().debug_str(PROGRAM, debugger).unwrap_err();
+    assert_eq!(err.to_string(), "evaluation error: stopped at 5");
+}
+
+#[test]
+fn console_reads_commands_and_shows_state() {
+    let commands = "b 2\nc\nbt\np x\nl\ne\nbogus\no\nd 2\nc\n";
+    let output = OutputBuffer::new();
+    let console = Console::new(PROGRAM, Input::new(Cursor::new(commands)), output.clone());
+    let mut interpreter = Interpreter::bare();
+    let printed = OutputBuffer::new();
+    interpreter.set_output(printed.clone());
+    let result = interpreter.debug_str(PROGRAM, Debugger::new(console));
+    assert!(matches!(result, Ok(Value::Number(3))));
+    assert_eq!(printed.contents(), "ri\n");
+    assert_eq!(
+        output.contents(),
+        "\
+line 1: gakasdenu square (x) (
+(debug) breakpoint at line 2
+(debug) line 2 (breakpoint): nitas x x
+(debug) #0 square
+#1 sum-squares
+#2 <top level>
+(debug) x = ru
+(debug)     1 gakasdenu square (x) (
+>   2     nitas x x
+    3 )
+    4 gakasdenu sum-squares (a b) (
+    5     (gakas total (ritas (square a) (square b)))
+(debug) scope 0: x = ru
+root: args = [], square = <function (x)>, sum-squares = <function (a b)>
+(debug) unknown command 'bogus'; try 'help'
+(debug) line 2 (breakpoint): nitas x x
+(debug) (debug) "
+    );
+}
+
+#[test]
+fn debug_subcommand_drives_the_console() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("squares.st");
+    fs::write(&path, PROGRAM).unwrap();
+
+    let mut child = Process::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("debug")
+        .arg("--break")
+        .arg("6")
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"p total\nc\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "line 6 (breakpoint): (total)\n(debug) total = ri\n(debug) ri\nro\n"
+    );
+
+    let mut child = Process::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("debug")
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"q\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "line 1: gakasdenu square (x) (\n(debug) "
+    );
+}
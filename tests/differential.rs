@@ -0,0 +1,71 @@
+mod common;
+
+use satukitan_rs::checker::{Severity, check_program};
+use satukitan_rs::parser::parse_program;
+use satukitan_rs::{Backend, Interpreter};
+
+/// Programs not covered by the other test files: evaluation order,
+/// auto-application of builtin arguments and error cases. Every test that
+/// evaluates through `common` is compared across backends, the optimizer and
+/// the debugger in the same way.
+const CASES: &[&str] = &[
+    // evaluation order and auto-application of builtin arguments
+    "ritas ra nitas ro re ru",
+    "[ru ritas ra ro re]",
+    "rakas [ra ra ra ru re]",
+    "gatas [ru ra] [ru ra]",
+    "kenus ga teses me me",
+    "()",
+    "(ritas ra ru) (nitas ra ro)",
+    "gakasdenu f (x) (x)\nf",
+    "gakasdenu twice (x) ((gakas y (ritas x x)) (ritas y ru))\ntwice ro",
+    "gakas s \"a # b\" # comment\n[s]",
+    // errors
+    "undefined-name",
+    "nobu ru ra ro",
+    "nobu me ra",
+    "gakas ra ru",
+    "gakasdenu f x (x)",
+    "gakasdenu f (ra) (ra)",
+    "gakasdenu f (x) (x)\nf ru ra",
+    "ritas ru \"ra\"",
+    "ditas ru ra ro",
+    "gakas x ru\nx ra",
+    r#"json-parse "[1,""#,
+];
+
+#[test]
+fn every_mode_agrees_on_all_cases() {
+    for source in CASES {
+        common::run(source);
+    }
+}
+
+#[test]
+fn checker_accepts_every_program_that_runs() {
+    for source in CASES {
+        if common::eval(source).is_err() {
+            continue;
+        }
+        let program = parse_program(source).unwrap();
+        let diagnostics = check_program(&program, &Interpreter::new().environment().borrow());
+        let errors = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{source:?}: {errors:?}");
+    }
+}
+
+#[test]
+fn backends_share_session_state() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("gakasdenu fibo (n) (nobu (ditasgata n ru) (n) (ritas (fibo (matyes n ru)) (fibo (matyes n ra))))")
+        .expect("definition should succeed");
+    interpreter.set_backend(Backend::Bytecode);
+    let result = interpreter
+        .eval_str("fibo ryu")
+        .expect("vm should call tree-walker function");
+    assert_eq!(result.to_string(), "13");
+}
@@ -0,0 +1,147 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+mod common;
+
+use common::{BACKENDS, run_with};
+use satukitan_rs::value::{Arity, BuiltinFunction, Value};
+use satukitan_rs::{Backend, Interpreter, SatukitanError};
+
+#[test]
+fn registered_closure_captures_host_state() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = log.clone();
+        interpreter.register_fn("kiroku", Arity::Exact(1), move |args| {
+            sink.borrow_mut().push(args[0].as_number()?);
+            Ok(Value::Nil)
+        });
+
+        interpreter
+            .eval_str("(kiroku ru) (kiroku ritas ra ro)")
+            .expect("evaluation should succeed");
+        assert_eq!(*log.borrow(), vec![1, 5]);
+    }
+}
+
+#[test]
+fn registered_builtin_is_auto_applied_as_argument() {
+    let run = run_with(
+        |interpreter| {
+            interpreter.register_fn("baiyes", Arity::Exact(1), |args| {
+                Ok(Value::Number(args[0].as_number()? * 2))
+            });
+        },
+        "(ritas baiyes ro ru)",
+    );
+    assert!(matches!(run.result, Ok(Value::Number(7))));
+}
+
+#[test]
+fn registered_builtin_arity_is_checked() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter.register_fn("baiyes", Arity::Exact(1), |args| Ok(args[0].clone()));
+        let err = interpreter.call("baiyes", []).unwrap_err();
+        assert!(matches!(
+            err,
+            SatukitanError::ArityMismatch { ref name, found: 0, .. } if name == "baiyes"
+        ));
+    }
+}
+
+#[test]
+fn registration_keeps_documentation_and_annotation() {
+    let mut interpreter = Interpreter::new();
+    let name = String::from("baiyes");
+    interpreter.register(
+        BuiltinFunction::new(&name, Arity::Exact(1), |args| Ok(args[0].clone()))
+            .with_doc("Doubles a number.")
+            .with_annotation("(num -> num)"),
+    );
+    interpreter.register_fn("baiyes", Arity::Exact(1), |args| Ok(args[0].clone()));
+
+    let [builtin] = interpreter.host_functions() else {
+        panic!("re-registering should replace the previous entry");
+    };
+    assert_eq!(builtin.name, "baiyes");
+    assert_eq!(builtin.doc(), None);
+
+    interpreter.register(
+        BuiltinFunction::new("kiroku", Arity::Any, |_| Ok(Value::Nil))
+            .with_doc("Records values.")
+            .with_annotation("(value -> nil)"),
+    );
+    let kiroku = &interpreter.host_functions()[1];
+    assert_eq!(kiroku.doc(), Some("Records values."));
+    assert_eq!(kiroku.annotation(), Some("(value -> nil)"));
+}
+
+#[test]
+fn call_invokes_script_functions_by_name() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter
+            .eval_str(
+                "(gakasdenu fibo (n) (nobu (ditas n ra) n (ritas (fibo (matyes n ru)) (fibo (matyes n ra)))))",
+            )
+            .unwrap();
+        let result = interpreter.call("fibo", [Value::from(10)]).unwrap();
+        assert!(matches!(result, Value::Number(55)), "{backend:?}");
+
+        let sum = interpreter
+            .call("ritas", [1.into(), 2.into(), 3.into()])
+            .unwrap();
+        assert!(matches!(sum, Value::Number(6)), "{backend:?}");
+    }
+}
+
+#[test]
+fn call_reports_missing_and_non_callable_names() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter.eval_str("(gakas x ru)").unwrap();
+        assert!(matches!(
+            interpreter.call("nai", []),
+            Err(SatukitanError::UndefinedSymbol(name)) if name == "nai"
+        ));
+        assert!(matches!(
+            interpreter.call("x", []),
+            Err(SatukitanError::Eval(_))
+        ));
+    }
+}
+
+#[test]
+fn call_recovers_after_a_failing_function() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter
+            .eval_str("(gakasdenu warui (n) (ritas n me))")
+            .unwrap();
+        assert!(interpreter.call("warui", [Value::from(1)]).is_err());
+        let result = interpreter.eval_str("ritas ru ra").unwrap();
+        assert!(matches!(result, Value::Number(3)), "{backend:?}");
+    }
+}
+
+#[test]
+fn reset_discards_bindings_but_keeps_configuration() {
+    let mut interpreter = Interpreter::with_backend(Backend::Bytecode);
+    interpreter.register_fn("host-one", Arity::Exact(0), |_| Ok(Value::Number(1)));
+    interpreter.set_args(["a".to_string()]);
+    interpreter.eval_str("gakas x ra").unwrap();
+
+    interpreter.reset();
+    assert_eq!(interpreter.backend(), Backend::Bytecode);
+    assert!(matches!(
+        interpreter.eval_str("x"),
+        Err(SatukitanError::UndefinedSymbol(_))
+    ));
+    let result = interpreter
+        .eval_str("[(host-one) (max ra ro) args]")
+        .unwrap();
+    assert_eq!(result.to_string(), "[ru ro [a]]");
+    assert_eq!(interpreter.host_functions().len(), 1);
+}
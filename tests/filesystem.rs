@@ -0,0 +1,125 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::process::Command;
+
+mod common;
+
+use common::{BACKENDS, run_with};
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+fn quoted(path: &std::path::Path) -> String {
+    format!("{:?}", path.to_str().expect("utf-8 temp path"))
+}
+
+/// Evaluates `source` like [`common::eval`], allowed to touch the
+/// filesystem.
+fn eval(source: &str) -> Result<Value, SatukitanError> {
+    run_with(|interpreter| interpreter.set_fs_access(true), source).result
+}
+
+#[test]
+fn files_can_be_written_appended_and_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = quoted(&dir.path().join("out.txt"));
+    let source =
+        format!("(write-file {path} \"ru\\n\") (append-file {path} \"ra\\n\") (read-file {path})");
+    let result = eval(&source).unwrap();
+    assert!(matches!(result, Value::String(ref s) if &**s == "ru\nra\n"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("out.txt")).unwrap(),
+        "ru\nra\n"
+    );
+}
+
+#[test]
+fn directories_are_listed_in_sorted_order() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("b.st"), "").unwrap();
+    fs::write(dir.path().join("a.st"), "").unwrap();
+    let dir_path = quoted(dir.path());
+    let listed = eval(&format!("list-dir {dir_path}")).unwrap();
+    assert_eq!(listed.to_string(), "[a.st b.st]");
+
+    let exists = eval(&format!(
+        "[(file-exists {}) (file-exists {})]",
+        quoted(&dir.path().join("a.st")),
+        quoted(&dir.path().join("missing.st"))
+    ))
+    .unwrap();
+    assert_eq!(exists.to_string(), "[me ga]");
+}
+
+#[test]
+fn io_errors_map_to_the_io_variant() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = quoted(&dir.path().join("missing.txt"));
+    let err = eval(&format!("read-file {missing}")).unwrap_err();
+    assert!(matches!(err, SatukitanError::Io(ref e) if e.kind() == ErrorKind::NotFound));
+}
+
+#[test]
+fn filesystem_access_is_opt_in() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("blocked.txt");
+    let path = quoted(&target);
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        assert!(!interpreter.fs_access(), "disabled by default");
+
+        for source in [
+            format!("write-file {path} \"x\""),
+            format!("read-file {path}"),
+            format!("file-exists {path}"),
+            format!("list-dir {}", quoted(dir.path())),
+        ] {
+            let err = interpreter.eval_str(&source).unwrap_err();
+            assert!(
+                matches!(err, SatukitanError::Io(ref e) if e.kind() == ErrorKind::PermissionDenied),
+                "{backend:?} {source}: {err}"
+            );
+        }
+        assert!(!target.exists());
+
+        interpreter.set_fs_access(true);
+        interpreter
+            .eval_str(&format!("write-file {path} \"x\""))
+            .unwrap();
+        assert!(target.exists());
+        fs::remove_file(&target).unwrap();
+    }
+}
+
+#[test]
+fn the_command_line_enables_access_unless_told_not_to() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("touch.st");
+    let target = dir.path().join("touched.txt");
+    fs::write(&script, format!("write-file {} \"x\"", quoted(&target))).unwrap();
+
+    let denied = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("--no-fs")
+        .arg("run")
+        .arg(&script)
+        .output()
+        .unwrap();
+    assert!(!denied.status.success());
+    assert!(!target.exists());
+
+    let allowed = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("run")
+        .arg(&script)
+        .output()
+        .unwrap();
+    assert!(allowed.status.success());
+    assert!(target.exists());
+}
+
+#[test]
+fn paths_must_be_strings() {
+    let err = eval("read-file ru").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "evaluation error: read-file: expected string, found number"
+    );
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::process::Command;
+
+use satukitan_rs::formatter::format_source;
+use satukitan_rs::interpreter::PRELUDE;
+use satukitan_rs::parser::parse_program;
+
+const MESSY: &str = r#"# header
+
+
+gakas x   ra   # trailing
+gakasdenu f (a b) ( # after open
+  nobu (fityes a b) # cond
+     (ritas a
+        b)
+  # own line
+     (a) )
+
+
+
+sipus [ru ra   ro]
+gakasdenu g (x) (  (gakasdenu h (y) (y))  )
+(ritas
+   ra ru)
+"#;
+
+const MESSY_FORMATTED: &str = r#"# header
+
+gakas x ra # trailing
+gakasdenu f (a b) ( # after open
+    nobu (fityes a b) # cond
+        (ritas a b)
+        # own line
+        (a)
+)
+
+sipus [ru ra ro]
+gakasdenu g (x) (
+    (gakasdenu h (y) (
+        y
+    ))
+)
+(ritas ra ru)
+"#;
+
+fn sources() -> Vec<&'static str> {
+    vec![
+        MESSY,
+        PRELUDE,
+        include_str!("../examples/fibonacci.st"),
+        include_str!("../examples/hello.st"),
+        "gakasdenu fibo (n) (nobu (ditas n ra) (n) (ritas (fibo (matyes n ru)) (fibo (matyes n ra))))\nsipus (fibo ryu)",
+        "nobu me (sipus ru) (sipus ra)",
+        "gakasdenu noop () ()",
+        "",
+    ]
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for source in sources() {
+        let once = format_source(source).unwrap();
+        assert_eq!(format_source(&once).unwrap(), once, "{source}");
+    }
+}
+
+#[test]
+fn formatting_preserves_the_program() {
+    for source in sources() {
+        let formatted = format_source(source).unwrap();
+        assert_eq!(
+            parse_program(&formatted).unwrap(),
+            parse_program(source).unwrap(),
+            "{formatted}"
+        );
+    }
+}
+
+#[test]
+fn comments_and_layout_follow_the_canonical_style() {
+    assert_eq!(format_source(MESSY).unwrap(), MESSY_FORMATTED);
+}
+
+#[test]
+fn bodies_and_branches_are_indented() {
+    let source = "gakasdenu fibo (n) (nobu (ditas n ra) (n) (ritas (fibo (matyes n ru)) (fibo (matyes n ra))))\n";
+    let expected = "\
+gakasdenu fibo (n) (
+    nobu (ditas n ra)
+        (n)
+        (ritas (fibo (matyes n ru)) (fibo (matyes n ra)))
+)
+";
+    assert_eq!(format_source(source).unwrap(), expected);
+}
+
+#[test]
+fn comments_inside_nobu_keep_the_branch_layout() {
+    let source = "gakasdenu f (x) (\n  nobu x # why\n    (ru)\n  # otherwise\n    (ra) # two\n)\n";
+    let expected = "\
+gakasdenu f (x) (
+    nobu x # why
+        (ru)
+        # otherwise
+        (ra) # two
+)
+";
+    assert_eq!(format_source(source).unwrap(), expected);
+    assert_eq!(format_source(expected).unwrap(), expected);
+}
+
+#[test]
+fn bodies_keep_one_expression_per_line() {
+    let source =
+        "gakasdenu f (a b) (\n(gakas total (ritas a b))   # sum\n        (total))\n\n\n# end\n\n";
+    let expected = "\
+gakasdenu f (a b) (
+    (gakas total (ritas a b)) # sum
+    (total)
+)
+
+# end
+";
+    assert_eq!(format_source(source).unwrap(), expected);
+}
+
+#[test]
+fn top_level_arguments_stay_on_one_line() {
+    let source = "nobu me   (sipus ru) (sipus ra)\n";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "nobu me (sipus ru) (sipus ra)\n"
+    );
+}
+
+#[test]
+fn bundled_sources_are_formatted() {
+    assert_eq!(format_source(PRELUDE).unwrap(), PRELUDE);
+    let fibonacci = include_str!("../examples/fibonacci.st");
+    assert_eq!(format_source(fibonacci).unwrap(), fibonacci);
+}
+
+#[test]
+fn invalid_sources_are_rejected() {
+    assert!(format_source("sipus (ritas ru").is_err());
+}
+
+#[test]
+fn fmt_rewrites_files_and_check_reports_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("messy.st");
+    fs::write(&path, MESSY).unwrap();
+
+    let check = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .args(["fmt", "--check"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(check.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&check.stdout).contains("would reformat"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), MESSY);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("fmt")
+        .arg(&path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), MESSY_FORMATTED);
+
+    let check = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .args(["fmt", "--check"])
+        .arg(&path)
+        .status()
+        .unwrap();
+    assert!(check.success());
+}
@@ -0,0 +1,74 @@
+use std::io::Cursor;
+
+mod common;
+
+use common::{BACKENDS, run_with};
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+/// Evaluates `source` reading `text` as its input, in every mode.
+fn with_input(text: &str, source: &str) -> Result<Value, SatukitanError> {
+    run_with(
+        |interpreter| interpreter.set_input(Cursor::new(text.to_string())),
+        source,
+    )
+    .result
+}
+
+#[test]
+fn read_line_strips_line_endings_and_returns_nil_at_eof() {
+    let result = with_input(
+        "ru\r\nra\nro",
+        "[(read-line) (read-line) (read-line) (read-line)]",
+    )
+    .unwrap();
+    assert_eq!(result.to_string(), "[ru ra ro nil]");
+}
+
+#[test]
+fn read_all_consumes_the_rest() {
+    let result = with_input(
+        "first\nsecond\nthird\n",
+        "[(read-line) (read-all) (read-all)]",
+    )
+    .unwrap();
+    let Value::List(items) = result else {
+        panic!("expected list");
+    };
+    assert!(matches!(&items[1], Value::String(s) if &**s == "second\nthird\n"));
+    assert!(items[2].is_nil());
+}
+
+#[test]
+fn lines_iterator_yields_each_line() {
+    let result = with_input(
+        "a\nb\n",
+        "gakas next (lines-iterator)\n[(next) (next) (next)]",
+    )
+    .unwrap();
+    assert_eq!(result.to_string(), "[a b nil]");
+}
+
+#[test]
+fn lines_iterator_drives_a_loop_until_eof() {
+    let source = r#"
+        gakas next (lines-iterator)
+        gakasdenu eof () ()
+        gakasdenu count (n) (nobu (gatas (next) (eof)) n (count (ritas n ru)))
+        count rv
+    "#;
+    let result = with_input("x\ny\nz\n", source).unwrap();
+    assert!(matches!(result, Value::Number(3)));
+}
+
+#[test]
+fn input_can_be_replaced_between_evaluations() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter.set_input(Cursor::new("ru\n"));
+        interpreter.eval_str("(read-line)").unwrap();
+        interpreter.set_input(Cursor::new("ra\n"));
+        let result = interpreter.eval_str("(read-line)").unwrap();
+        assert!(matches!(result, Value::String(ref s) if &**s == "ra"));
+    }
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::eval;
+use satukitan_rs::SatukitanError;
+use satukitan_rs::json::{self, Json};
+use satukitan_rs::value::Value;
+
+#[test]
+fn json_parse_maps_documents_onto_values() {
+    let value =
+        eval(r#"json-parse "{\"name\": \"satu\", \"tags\": [1, -2, true, null]}""#).unwrap();
+    assert_eq!(value.to_string(), "[[name satu] [tags [ru -2 me nil]]]");
+}
+
+#[test]
+fn json_stringify_encodes_data_values() {
+    let value = eval(r#"json-stringify [ru "a\"b" [me ga] (fanitas [ro ra])]"#).unwrap();
+    assert!(matches!(value, Value::String(ref s) if &**s == r#"[1,"a\"b",[true,false],[2,3]]"#));
+}
+
+#[test]
+fn json_round_trips_through_builtins() {
+    let source = r#"
+        gakas text (json-stringify [ru [ra "x\ny"]])
+        gatas (json-parse text) [ru [ra "x\ny"]]
+    "#;
+    assert!(matches!(eval(source).unwrap(), Value::Bool(true)));
+}
+
+#[test]
+fn escapes_and_unicode_are_decoded() {
+    let value = json::parse(r#""tab\té😀\/""#).unwrap();
+    assert!(matches!(value, Value::String(ref s) if &**s == "tab\té😀/"));
+    assert_eq!(
+        json::stringify(&Value::String("\u{1}\"".into())).unwrap(),
+        r#""\u0001\"""#
+    );
+}
+
+#[test]
+fn malformed_input_is_reported() {
+    for input in [
+        "",
+        "[1,",
+        "[1 2]",
+        "{\"a\" 1}",
+        "{1: 2}",
+        "01",
+        "1.5",
+        "1e3",
+        "tru",
+        "\"abc",
+        "[] []",
+        "99999999999999999999",
+        r#""\x""#,
+        r#""\ud800""#,
+    ] {
+        assert!(
+            matches!(json::parse(input), Err(SatukitanError::Json(_))),
+            "{input:?} should be rejected"
+        );
+    }
+    let err = json::parse("[1, ?]").unwrap_err();
+    assert_eq!(err.to_string(), "json error: unexpected token at byte 4");
+    assert!(json::parse(&"[".repeat(10_000)).is_err());
+}
+
+#[test]
+fn callables_cannot_be_encoded() {
+    let err = eval("gakasdenu f (x) (x)\njson-stringify [f]").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "json error: cannot encode a function value"
+    );
+    assert!(matches!(
+        eval("json-parse ru"),
+        Err(SatukitanError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn json_documents_keep_objects() {
+    let document = Json::parse(r#"{"id": 3, "params": {"uri": "a\nb", "list": [null]}}"#).unwrap();
+    assert_eq!(document.get("id").and_then(Json::as_i64), Some(3));
+    let params = document.get("params").unwrap();
+    assert_eq!(params.get("uri").and_then(Json::as_str), Some("a\nb"));
+    assert_eq!(
+        params.get("list").and_then(Json::as_array),
+        Some(&[Json::Null][..])
+    );
+    assert_eq!(
+        document.to_string(),
+        r#"{"id":3,"params":{"uri":"a\nb","list":[null]}}"#
+    );
+}
@@ -1,4 +1,4 @@
-use satukitan_rs::lexer::{identifier, inline_space0, inline_space1};
+use satukitan_rs::lexer::{TokenKind, identifier, inline_space0, inline_space1, tokenize};
 
 #[test]
 fn inline_space0_accepts_mixed_whitespace() {
@@ -27,8 +27,65 @@ fn identifier_supports_extended_tokens() {
     assert_eq!(rest_hash, " rest");
 }
 
+#[test]
+fn identifier_accepts_module_prefixes() {
+    let (rest, ident) = identifier("math.square ru").expect("should parse dotted identifier");
+    assert_eq!(ident, "math.square");
+    assert_eq!(rest, " ru");
+    assert!(identifier(".square").is_err());
+}
+
 #[test]
 fn identifier_rejects_invalid_start() {
     assert!(identifier("9start").is_err());
     assert!(identifier("-dash").is_err());
 }
+
+fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+    tokenize(source)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .map(|token| (token.kind, token.text))
+        .collect()
+}
+
+#[test]
+fn tokenize_covers_the_whole_source() {
+    let source = "gakasdenu f (x) [#ta \"a\\\"b\" me] # note\n9 )";
+    let tokens = tokenize(source);
+    assert_eq!(tokens.iter().map(|t| t.text).collect::<String>(), source);
+    assert!(
+        tokens
+            .iter()
+            .all(|token| &source[token.start..token.start + token.text.len()] == token.text)
+    );
+    assert_eq!(
+        kinds(source),
+        [
+            (TokenKind::Identifier, "gakasdenu"),
+            (TokenKind::Identifier, "f"),
+            (TokenKind::Open, "("),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::Close, ")"),
+            (TokenKind::Open, "["),
+            (TokenKind::Identifier, "#ta"),
+            (TokenKind::String, "\"a\\\"b\""),
+            (TokenKind::Identifier, "me"),
+            (TokenKind::Close, "]"),
+            (TokenKind::Comment, "# note"),
+            (TokenKind::Unknown, "9"),
+            (TokenKind::Close, ")"),
+        ]
+    );
+}
+
+#[test]
+fn tokenize_keeps_unterminated_strings() {
+    assert_eq!(
+        kinds("sipus \"open ("),
+        [
+            (TokenKind::Identifier, "sipus"),
+            (TokenKind::String, "\"open ("),
+        ]
+    );
+}
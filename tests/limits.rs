@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+mod common;
+
+use common::BACKENDS;
+use satukitan_rs::limits::{DEFAULT_MAX_CALL_DEPTH, Limits};
+use satukitan_rs::value::Value;
+use satukitan_rs::{Backend, Interpreter, SatukitanError};
+
+const FIBO: &str = r#"
+    gakasdenu fibo (n) (
+        nobu (ditasgata n ru)
+            (n)
+            (ritas (fibo (matyes n ru)) (fibo (matyes n ra)))
+    )
+"#;
+
+fn limited(backend: Backend, limits: Limits) -> Interpreter {
+    let mut interpreter = Interpreter::with_backend(backend);
+    interpreter.set_limits(limits);
+    interpreter
+}
+
+#[test]
+fn step_budget_stops_infinite_recursion() {
+    for backend in BACKENDS {
+        let mut interpreter = limited(
+            backend,
+            Limits {
+                max_steps: Some(500),
+                ..Limits::default()
+            },
+        );
+        let err = interpreter
+            .eval_str("gakasdenu spin (n) (spin n)\nspin ru")
+            .expect_err("evaluation should run out of steps");
+        assert!(matches!(err, SatukitanError::StepLimitExceeded(500)));
+    }
+}
+
+#[test]
+fn call_depth_limit_stops_infinite_recursion() {
+    for backend in BACKENDS {
+        let mut interpreter = limited(
+            backend,
+            Limits {
+                max_call_depth: Some(64),
+                ..Limits::default()
+            },
+        );
+        let err = interpreter
+            .eval_str("gakasdenu spin (n) (spin n)\nspin ru")
+            .expect_err("evaluation should recurse too deeply");
+        assert!(matches!(err, SatukitanError::RecursionLimitExceeded(64)));
+    }
+}
+
+#[test]
+fn a_step_budget_also_bounds_the_call_depth() {
+    for backend in BACKENDS {
+        let mut interpreter = limited(
+            backend,
+            Limits {
+                max_steps: Some(10_000_000),
+                ..Limits::default()
+            },
+        );
+        let err = interpreter
+            .eval_str("gakasdenu spin (n) (ritas ru (spin n))\nspin ru")
+            .expect_err("evaluation should recurse too deeply");
+        assert!(
+            matches!(
+                err,
+                SatukitanError::RecursionLimitExceeded(DEFAULT_MAX_CALL_DEPTH)
+            ),
+            "{backend:?}: {err}"
+        );
+    }
+}
+
+#[test]
+fn deep_recursion_does_not_depend_on_the_native_stack() {
+    let source = "gakasdenu down (n) (nobu (ditasgata n rv) (rv) (ritas ru (down (matyes n ru))))\n\
+                  down (nitas ro #ta #ta #ta)";
+    std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(move || {
+            for backend in BACKENDS {
+                let result = Interpreter::with_backend(backend).eval_str(source);
+                assert!(matches!(result, Ok(Value::Number(3000))), "{backend:?}");
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn deadline_stops_long_evaluation() {
+    for backend in BACKENDS {
+        let mut interpreter = limited(
+            backend,
+            Limits {
+                timeout: Some(Duration::from_millis(5)),
+                ..Limits::default()
+            },
+        );
+        interpreter
+            .eval_str(FIBO)
+            .expect("definition should succeed");
+        let err = interpreter
+            .eval_str("fibo (nitas ri ri)")
+            .expect_err("evaluation should time out");
+        assert!(matches!(err, SatukitanError::TimeLimitExceeded(_)));
+    }
+}
+
+#[test]
+fn allocation_limits_reject_large_values() {
+    for backend in BACKENDS {
+        let mut interpreter = limited(
+            backend,
+            Limits {
+                max_list_len: Some(3),
+                max_string_len: Some(4),
+                ..Limits::default()
+            },
+        );
+        assert!(interpreter.eval_str("fanitas [ro ra ru]").is_ok());
+        let err = interpreter
+            .eval_str("fanitas [re ro ra ru]")
+            .expect_err("list should be too long");
+        assert!(matches!(
+            err,
+            SatukitanError::AllocationLimitExceeded {
+                kind: "list",
+                len: 4,
+                limit: 3
+            }
+        ));
+        let err = interpreter
+            .eval_str("range rv (nitas #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta)")
+            .expect_err("range should be too long");
+        assert!(matches!(
+            err,
+            SatukitanError::AllocationLimitExceeded {
+                kind: "list",
+                len: 1_000_000_000_000,
+                limit: 3
+            }
+        ));
+        let err = interpreter
+            .eval_str("\"sana sapotav!\"")
+            .expect_err("string should be too long");
+        assert!(matches!(
+            err,
+            SatukitanError::AllocationLimitExceeded { kind: "string", .. }
+        ));
+    }
+}
+
+#[test]
+fn limits_apply_per_evaluation() {
+    for backend in BACKENDS {
+        let mut interpreter = limited(
+            backend,
+            Limits {
+                max_steps: Some(2_000),
+                ..Limits::default()
+            },
+        );
+        interpreter
+            .eval_str(FIBO)
+            .expect("definition should succeed");
+        for _ in 0..5 {
+            interpreter
+                .eval_str("fibo ri")
+                .expect("each evaluation gets a fresh budget");
+        }
+    }
+}
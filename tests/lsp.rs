@@ -0,0 +1,283 @@
+use std::io::Cursor;
+use std::process::{Command, ExitCode, Stdio};
+
+use serde_json::{Value, json};
+
+use satukitan_rs::Interpreter;
+use satukitan_rs::lsp::serve;
+
+const URI: &str = "file:///work/main.st";
+
+fn frame(message: &Value) -> String {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{body}", body.len())
+}
+
+fn unframe(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while !output.is_empty() {
+        let (header, rest) = output.split_once("\r\n\r\n").unwrap();
+        let length: usize = header
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        messages.push(serde_json::from_str(&rest[..length]).unwrap());
+        output = &rest[length..];
+    }
+    messages
+}
+
+/// Runs a session that initializes the server, opens `source` as [`URI`],
+/// sends `requests` and shuts down. Returns what the server sent after the
+/// `initialize` response.
+fn session(source: &str, requests: &[Value]) -> Vec<Value> {
+    let mut input =
+        frame(&json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}));
+    input += &frame(&json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}));
+    input += &frame(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {"textDocument": {"uri": URI, "languageId": "satukitan", "version": 1, "text": source}},
+    }));
+    for request in requests {
+        input += &frame(request);
+    }
+    input += &frame(&json!({"jsonrpc": "2.0", "id": 99, "method": "shutdown"}));
+    input += &frame(&json!({"jsonrpc": "2.0", "method": "exit"}));
+
+    let mut output = Vec::new();
+    let code = serve(Cursor::new(input), &mut output, &Interpreter::new()).unwrap();
+    assert_eq!(code, ExitCode::SUCCESS);
+    let mut messages = unframe(&String::from_utf8(output).unwrap());
+    assert!(messages[0]["result"]["capabilities"].is_object());
+    assert_eq!(
+        messages.pop().unwrap(),
+        json!({"jsonrpc": "2.0", "id": 99, "result": null})
+    );
+    messages.remove(0);
+    messages
+}
+
+fn request(id: i64, method: &str, line: u32, character: u32) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {"textDocument": {"uri": URI}, "position": {"line": line, "character": character}},
+    })
+}
+
+#[test]
+fn opening_a_document_publishes_its_diagnostics() {
+    let messages = session("gakasdenu f (x) (ritas x y)\nf ru", &[]);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(messages[0]["params"]["uri"], URI);
+    assert_eq!(
+        messages[0]["params"]["diagnostics"],
+        json!([{
+            "range": {"start": {"line": 0, "character": 25}, "end": {"line": 0, "character": 26}},
+            "severity": 1,
+            "source": "satukitan",
+            "message": "undefined symbol: y",
+        }])
+    );
+}
+
+#[test]
+fn parse_errors_and_edits_update_the_diagnostics() {
+    let change = |text: &str| {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": text}]},
+        })
+    };
+    let messages = session(
+        "sipus (ritas ra]",
+        &[
+            change("gakas _x ru"),
+            json!({"jsonrpc": "2.0", "method": "textDocument/didClose", "params": {"textDocument": {"uri": URI}}}),
+        ],
+    );
+    let diagnostics = messages
+        .iter()
+        .map(|message| message["params"]["diagnostics"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            json!([{
+                "range": {"start": {"line": 0, "character": 15}, "end": {"line": 0, "character": 15}},
+                "severity": 1,
+                "source": "satukitan",
+                "message": "parse error: unexpected token near '…]'",
+            }]),
+            json!([{
+                "range": {"start": {"line": 0, "character": 6}, "end": {"line": 0, "character": 8}},
+                "severity": 2,
+                "source": "satukitan",
+                "message": "unused binding '_x'",
+            }]),
+            json!([]),
+        ]
+    );
+}
+
+#[test]
+fn completion_offers_keywords_builtins_and_local_definitions() {
+    let messages = session(
+        "gakasdenu double (n) (ritas n n)\ndouble ru",
+        &[request(1, "textDocument/completion", 1, 0)],
+    );
+    let items = messages[1]["result"].as_array().unwrap();
+    let item = |label: &str| {
+        items
+            .iter()
+            .find(|item| item["label"] == label)
+            .unwrap_or_else(|| panic!("no completion for {label}"))
+    };
+    assert_eq!(items[0]["label"], "double");
+    assert_eq!(item("double")["detail"], "(n)");
+    assert_eq!(item("gakasdenu")["kind"], 14);
+    assert_eq!(item("ritas")["detail"], "(num num -> num)");
+    assert_eq!(item("ritas")["kind"], 3);
+    assert_eq!(item("#ta")["kind"], 21);
+    assert_eq!(item("max")["detail"], "(a b)");
+}
+
+#[test]
+fn hover_shows_numerals_and_signatures() {
+    let source = "gakasdenu double (n) (ritas n n)\nsipus (double rye)";
+    let messages = session(
+        source,
+        &[
+            request(1, "textDocument/hover", 1, 15),
+            request(2, "textDocument/hover", 0, 24),
+            request(3, "textDocument/hover", 1, 9),
+            request(4, "textDocument/hover", 0, 17),
+        ],
+    );
+    let hover = |idx: usize| messages[idx]["result"]["contents"]["value"].clone();
+    assert_eq!(hover(1), "rye : number = 9");
+    assert_eq!(
+        messages[1]["result"]["range"],
+        json!({"start": {"line": 1, "character": 14}, "end": {"line": 1, "character": 17}})
+    );
+    assert_eq!(hover(2), "ritas (num num -> num)");
+    assert_eq!(hover(3), "double : function (n)");
+    assert_eq!(messages[4]["result"], Value::Null);
+}
+
+#[test]
+fn definition_jumps_to_the_defining_name() {
+    let source = "gakasdenu outer (x) (\n    (gakasdenu inner (y) (y))\n    (inner x)\n)\nouter ru";
+    let messages = session(
+        source,
+        &[
+            request(1, "textDocument/definition", 4, 2),
+            request(2, "textDocument/definition", 2, 6),
+            request(3, "textDocument/definition", 4, 6),
+        ],
+    );
+    let location = |line: u32, start: u32, end: u32| {
+        json!([{
+            "uri": URI,
+            "range": {"start": {"line": line, "character": start}, "end": {"line": line, "character": end}},
+        }])
+    };
+    assert_eq!(messages[1]["result"], location(0, 10, 15));
+    assert_eq!(messages[2]["result"], location(1, 15, 20));
+    assert_eq!(messages[3]["result"], json!([]));
+}
+
+#[test]
+fn formatting_replaces_the_whole_document() {
+    let formatting = |id: i64| {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "textDocument/formatting",
+            "params": {"textDocument": {"uri": URI}, "options": {"tabSize": 4, "insertSpaces": true}},
+        })
+    };
+    let messages = session("gakas   x ra\n\n\n\nsipus x", &[formatting(1)]);
+    assert_eq!(
+        messages[1]["result"],
+        json!([{
+            "range": {"start": {"line": 0, "character": 0}, "end": {"line": 4, "character": 7}},
+            "newText": "gakas x ra\n\nsipus x\n",
+        }])
+    );
+    let messages = session("gakas x ra\n", &[formatting(1)]);
+    assert_eq!(messages[1]["result"], json!([]));
+}
+
+#[test]
+fn protocol_errors_are_reported() {
+    let mut input = frame(&json!({"jsonrpc": "2.0", "id": 1, "method": "textDocument/hover"}));
+    input += &frame(&json!({"jsonrpc": "2.0", "id": 2, "method": "initialize", "params": {}}));
+    input += &frame(&json!({"jsonrpc": "2.0", "id": 3, "method": "workspace/symbol"}));
+    input += "Content-Length: 5\r\n\r\n{oops";
+    input += &frame(&json!({"jsonrpc": "2.0", "method": "exit"}));
+
+    let mut output = Vec::new();
+    let code = serve(Cursor::new(input), &mut output, &Interpreter::new()).unwrap();
+    assert_eq!(code, ExitCode::FAILURE, "exit without shutdown fails");
+    let messages = unframe(&String::from_utf8(output).unwrap());
+    assert_eq!(messages[0]["error"]["code"], -32002);
+    assert_eq!(messages[1]["id"], 2);
+    assert_eq!(
+        messages[2]["error"],
+        json!({"code": -32601, "message": "unknown method: workspace/symbol"})
+    );
+    assert_eq!(messages[3]["id"], Value::Null);
+    assert_eq!(messages[3]["error"]["code"], -32700);
+}
+
+#[test]
+fn oversized_messages_are_refused() {
+    let input = "Content-Length: 99999999999\r\n\r\n{}";
+    let err = serve(Cursor::new(input), Vec::new(), &Interpreter::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "evaluation error: lsp: message of 99999999999 bytes exceeds the limit of 67108864 bytes"
+    );
+}
+
+#[test]
+fn formatting_keeps_nobu_branches_indented_around_comments() {
+    let formatting = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "textDocument/formatting",
+        "params": {"textDocument": {"uri": URI}, "options": {"tabSize": 4, "insertSpaces": true}},
+    });
+    let source = "gakasdenu f (x) (\n  nobu x # why\n    (ru)\n    (ra)\n)\n";
+    let messages = session(source, &[formatting]);
+    assert_eq!(
+        messages[1]["result"][0]["newText"],
+        "gakasdenu f (x) (\n    nobu x # why\n        (ru)\n        (ra)\n)\n"
+    );
+}
+
+#[test]
+fn lsp_subcommand_serves_stdio() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut input =
+        frame(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+    input += &frame(&json!({"jsonrpc": "2.0", "id": 2, "method": "shutdown"}));
+    input += &frame(&json!({"jsonrpc": "2.0", "method": "exit"}));
+    std::io::Write::write_all(&mut child.stdin.take().unwrap(), input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let messages = unframe(&String::from_utf8(output.stdout).unwrap());
+    assert_eq!(messages[0]["result"]["serverInfo"]["name"], "satukitan");
+    assert_eq!(messages[1]["result"], Value::Null);
+}
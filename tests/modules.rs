@@ -0,0 +1,137 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+mod common;
+
+use common::{BACKENDS, run_with};
+use satukitan_rs::interpreter::Backend;
+use satukitan_rs::io::OutputBuffer;
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+const MATH: &str = "gakasdenu square (x) (nitas x x)\ngakas _hidden ra\n";
+
+fn write(dir: &Path, name: &str, source: &str) {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, source).unwrap();
+}
+
+/// An interpreter allowed to read modules from disk.
+fn interpreter(backend: Backend) -> Interpreter {
+    let mut interpreter = Interpreter::with_backend(backend);
+    interpreter.set_fs_access(true);
+    interpreter
+}
+
+/// Runs `source` as `main.st` in `dir` on each backend and returns the
+/// result, after checking the backends agree.
+fn run_main(dir: &Path, source: &str) -> Result<Value, SatukitanError> {
+    write(dir, "main.st", source);
+    let [tree, vm] = BACKENDS.map(|backend| interpreter(backend).eval_file(dir.join("main.st")));
+    assert_eq!(format!("{tree:?}"), format!("{vm:?}"), "{source:?}");
+    tree
+}
+
+#[test]
+fn imports_bind_exports_under_the_file_stem() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "math.st", MATH);
+    let result = run_main(dir.path(), "import \"math\"\nmath.square ro").unwrap();
+    assert!(matches!(result, Value::Number(9)));
+}
+
+#[test]
+fn an_alias_replaces_the_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "math.st", MATH);
+    let result = run_main(dir.path(), "import \"math.st\" m\nm.square ra");
+    assert!(matches!(result, Ok(Value::Number(4))));
+}
+
+#[test]
+fn nested_imports_resolve_relative_to_the_importing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "lib/inner.st", "gakas base ro\n");
+    write(
+        dir.path(),
+        "lib/outer.st",
+        "import \"inner\"\ngakasdenu triple (x) (nitas x inner.base)\n",
+    );
+    let result = run_main(dir.path(), "import \"lib/outer\"\nouter.triple ra");
+    assert!(matches!(result, Ok(Value::Number(6))));
+}
+
+#[test]
+fn search_paths_are_used_after_the_importing_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "math.st", MATH);
+    let run = run_with(
+        |interpreter| {
+            interpreter.set_fs_access(true);
+            interpreter.add_module_path(dir.path());
+        },
+        "import \"math\"\nmath.square ra",
+    );
+    assert!(matches!(run.result, Ok(Value::Number(4))));
+}
+
+#[test]
+fn modules_run_once_and_private_names_stay_hidden() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "math.st", &format!("sipus \"loaded\"\n{MATH}"));
+    write(
+        dir.path(),
+        "main.st",
+        "import \"math\"\nimport \"math\" m\n",
+    );
+    for backend in BACKENDS {
+        let mut interpreter = interpreter(backend);
+        let buffer = OutputBuffer::new();
+        interpreter.set_output(buffer.clone());
+        interpreter.eval_file(dir.path().join("main.st")).unwrap();
+        assert_eq!(buffer.contents(), "loaded\n");
+
+        let hidden = interpreter.eval_str("math._hidden");
+        assert!(
+            matches!(hidden, Err(SatukitanError::UndefinedSymbol(ref name)) if name == "math._hidden")
+        );
+        assert!(matches!(
+            interpreter.eval_str("m.square ro"),
+            Ok(Value::Number(9))
+        ));
+    }
+}
+
+#[test]
+fn import_cycles_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "a.st", "import \"b\"\n");
+    write(dir.path(), "b.st", "import \"a\"\n");
+    let err = run_main(dir.path(), "import \"a\"").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "import error: import cycle: a.st -> b.st -> a.st"
+    );
+}
+
+#[test]
+fn missing_modules_and_disabled_filesystem_fail() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = run_main(dir.path(), "import \"nowhere\"").unwrap_err();
+    assert!(matches!(err, SatukitanError::Import(ref msg) if msg == "module 'nowhere' not found"));
+
+    write(dir.path(), "math.st", MATH);
+    // Embedders have to opt in to filesystem access.
+    let mut interpreter = Interpreter::new();
+    interpreter.add_module_path(dir.path());
+    let err = interpreter.eval_str("import \"math\"").unwrap_err();
+    assert!(matches!(err, SatukitanError::Io(ref e) if e.kind() == ErrorKind::PermissionDenied));
+}
+
+#[test]
+fn malformed_imports_are_rejected() {
+    let err = common::eval("import math").unwrap_err();
+    assert!(err.to_string().contains("import: expected a path string"));
+}
@@ -0,0 +1,106 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use satukitan_rs::Interpreter;
+use satukitan_rs::ast::Expr;
+use satukitan_rs::env::Environment;
+use satukitan_rs::optimizer::optimize_program;
+use satukitan_rs::parser::parse_program;
+use satukitan_rs::value::{Arity, Value};
+
+fn optimize(source: &str) -> Vec<Expr> {
+    let interpreter = Interpreter::new();
+    let env = interpreter.environment();
+    let program = parse_program(source).expect("parse failed");
+    optimize_program(&program, &env.borrow())
+}
+
+#[test]
+fn folds_pure_builtin_calls() {
+    assert_eq!(optimize("ritas ra ru"), vec![Expr::Number(3)]);
+    assert_eq!(
+        optimize("fanitas [ro ra ru]"),
+        vec![Expr::ListLiteral(vec![
+            Expr::Number(1),
+            Expr::Number(2),
+            Expr::Number(3),
+        ])]
+    );
+    assert_eq!(optimize("ritas ra nitas ro re ru"), vec![Expr::Number(15)]);
+}
+
+#[test]
+fn folds_builtin_arguments_but_keeps_side_effects() {
+    match &optimize("sipus matyes ra ru")[0] {
+        Expr::Call { func, args } => {
+            assert_eq!(func.as_symbol(), Some("sipus"));
+            assert_eq!(args, &vec![Expr::Number(1)]);
+        }
+        other => panic!("expected call, got {:?}", other),
+    }
+}
+
+#[test]
+fn eliminates_nobu_with_literal_condition() {
+    assert_eq!(
+        optimize("nobu (ditas ru ra) (ra) (ru)"),
+        vec![Expr::List(vec![Expr::Number(2)])]
+    );
+}
+
+#[test]
+fn leaves_failing_calls_alone() {
+    let program = optimize("ritas ru \"ra\"");
+    assert!(matches!(program[0], Expr::Call { .. }));
+}
+
+#[test]
+fn leaves_list_building_calls_to_run_under_the_limits() {
+    let program = optimize("range rv (nitas #ta #ta #ta)");
+    assert!(matches!(program[0], Expr::Call { .. }));
+}
+
+#[test]
+fn rebinding_a_builtin_disables_folding() {
+    let program = optimize("gakas ritas nitas\nritas ra ro");
+    assert!(matches!(program[1], Expr::Call { .. }));
+
+    let mut env = Environment::new();
+    env.define("ritas", Value::Number(0));
+    let program = parse_program("ritas ra ro").expect("parse failed");
+    let optimized = optimize_program(&program, &env);
+    assert!(matches!(optimized[0], Expr::Call { .. }));
+}
+
+#[test]
+fn inlines_trivial_functions() {
+    let program = optimize("gakasdenu double (x) (ritas x x)\ndouble ro");
+    assert_eq!(program[1], Expr::Number(6));
+
+    let program = optimize("gakasdenu shout (x) (sipus x)\nshout ro");
+    assert!(matches!(program[1], Expr::Call { .. }));
+}
+
+#[test]
+fn does_not_inline_before_definition_or_when_redefined() {
+    let program = optimize("double ro\ngakasdenu double (x) (ritas x x)");
+    assert!(matches!(program[0], Expr::Call { .. }));
+
+    let program = optimize("gakasdenu double (x) (ritas x x)\ngakas double ru\ndouble ro");
+    assert!(matches!(program[2], Expr::Call { .. }));
+}
+
+#[test]
+fn host_builtins_are_never_folded() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_optimize(true);
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    interpreter.register_fn("ritas", Arity::AtLeast(2), move |_| {
+        counter.set(counter.get() + 1);
+        Ok(Value::Number(0))
+    });
+    interpreter.eval_str("(ritas ru ra)").unwrap();
+    interpreter.eval_str("(ritas ru ra)").unwrap();
+    assert_eq!(calls.get(), 2);
+}
@@ -1,5 +1,5 @@
 use satukitan_rs::ast::Expr;
-use satukitan_rs::parser::parse_program;
+use satukitan_rs::parser::{Completeness, completeness, parse_program};
 
 #[test]
 fn parse_simple_call() {
@@ -51,3 +51,31 @@ fn parse_gakas_two_args() {
         other => panic!("expected call, got {:?}", other),
     }
 }
+
+#[test]
+fn completeness_tracks_open_brackets_and_strings() {
+    assert_eq!(completeness("ritas ra ru"), Completeness::Complete);
+    assert_eq!(
+        completeness("gakasdenu add-two (x y) (\n    ritas x y"),
+        Completeness::Incomplete
+    );
+    assert_eq!(
+        completeness("gakasdenu add-two (x y) (\n    ritas x y\n)"),
+        Completeness::Complete
+    );
+    assert_eq!(completeness("[ru (ra"), Completeness::Incomplete);
+    assert_eq!(completeness("sipus \"a ( \\\" b"), Completeness::Incomplete);
+    assert_eq!(completeness("sipus \"(\" # (\n"), Completeness::Complete);
+}
+
+#[test]
+fn completeness_rejects_mismatched_closers() {
+    assert_eq!(
+        completeness("ritas ru )"),
+        Completeness::Invalid("unexpected ')'".to_string())
+    );
+    assert_eq!(
+        completeness("[ru (ra])"),
+        Completeness::Invalid("expected ')', found ']'".to_string())
+    );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use satukitan_rs::interpreter::PRELUDE;
+use satukitan_rs::parser::parse_program;
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+fn eval(source: &str) -> String {
+    common::eval(source)
+        .expect("evaluation should succeed")
+        .to_string()
+}
+
+#[test]
+fn prelude_loads_cleanly() {
+    assert!(parse_program(PRELUDE).is_ok());
+    let mut interpreter = Interpreter::bare();
+    assert!(interpreter.eval_str(PRELUDE).is_ok());
+    for name in ["max", "min", "compose"] {
+        let value = Interpreter::new().environment().borrow().get(name);
+        assert!(matches!(value, Some(Value::Function(_))), "{name}");
+    }
+    for name in ["sum", "range"] {
+        let value = Interpreter::bare().environment().borrow().get(name);
+        assert!(matches!(value, Some(Value::Builtin(_))), "{name}");
+    }
+}
+
+#[test]
+fn numeric_helpers() {
+    assert_eq!(eval("[(max ra ro) (min ra ro)]"), "[ro ra]");
+    assert_eq!(eval("sum [ru ra ro]"), "rya");
+    assert_eq!(eval("sum []"), "rv");
+}
+
+#[test]
+fn sum_reports_overflow() {
+    let big = "(nitas #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta #ta)";
+    let err = common::eval(&format!(
+        "sum [{big} {big} {big} {big} {big} {big} {big} {big} {big} {big}]"
+    ))
+    .unwrap_err();
+    assert!(matches!(err, SatukitanError::Eval(ref msg) if msg == "sum: integer overflow"));
+}
+
+#[test]
+fn range_is_half_open() {
+    assert_eq!(eval("range ru re"), "[ru ra ro]");
+    assert_eq!(eval("range ro ru"), "[]");
+}
+
+#[test]
+fn long_ranges_do_not_overflow_the_stack() {
+    let source = "gakas xs (range rv (nitas #ta #ta #ta #ta))\n[(rakas xs) (sum xs)]";
+    assert_eq!(eval(source), "[10000 49995000]");
+}
+
+#[test]
+fn compose_applies_right_to_left() {
+    let source = r#"
+        gakasdenu double (x) (ritas x x)
+        gakasdenu inc (x) (ritas x ru)
+        gakas f (compose double inc)
+        f ro
+    "#;
+    assert_eq!(eval(source), "ryo");
+}
+
+#[test]
+fn list_primitives() {
+    assert_eq!(
+        eval("[(head [ru ra]) (tail [ru ra]) (cons rv [ru])]"),
+        "[ru [ra] [rv ru]]"
+    );
+    let err = common::eval("head []").unwrap_err();
+    assert!(matches!(err, SatukitanError::Eval(ref msg) if msg == "head: empty list"));
+}
+
+#[test]
+fn bare_interpreters_skip_the_prelude() {
+    let mut interpreter = Interpreter::bare();
+    assert!(matches!(
+        interpreter.eval_str("max ru ra"),
+        Err(SatukitanError::UndefinedSymbol(ref name)) if name == "max"
+    ));
+    assert!(matches!(
+        interpreter.eval_str("ritas ru ra"),
+        Ok(Value::Number(3))
+    ));
+}
+
+#[test]
+fn modules_see_the_prelude() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("util.st"), "gakas total (sum [ru ra])\n").unwrap();
+    let run = common::run_with(
+        |interpreter| {
+            interpreter.set_fs_access(true);
+            interpreter.add_module_path(dir.path());
+        },
+        "import \"util\"\nutil.total",
+    );
+    assert!(matches!(run.result, Ok(Value::Number(3))));
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+use common::{BACKENDS, eval, run_with};
+use satukitan_rs::value::Value;
+use satukitan_rs::{Interpreter, SatukitanError};
+
+#[test]
+fn args_default_to_an_empty_list() {
+    let result = eval("rakas args").unwrap();
+    assert!(matches!(result, Value::Number(0)));
+}
+
+#[test]
+fn args_are_bound_as_a_list_of_strings() {
+    let run = run_with(
+        |interpreter| interpreter.set_args(["input.txt".to_string(), "-v".to_string()]),
+        "args",
+    );
+    assert_eq!(run.result.unwrap().to_string(), "[input.txt -v]");
+}
+
+/// Evaluates `source` with the process builtins enabled.
+fn eval_with_process(source: &str) -> Result<Value, SatukitanError> {
+    run_with(|interpreter| interpreter.set_process_access(true), source).result
+}
+
+#[test]
+fn process_builtins_are_undefined_by_default() {
+    let mut interpreter = Interpreter::new();
+    assert!(!interpreter.process_access());
+    for name in ["getenv", "exit"] {
+        assert!(matches!(
+            eval(&format!("{name} \"HOME\"")),
+            Err(SatukitanError::UndefinedSymbol(ref undefined)) if undefined == name
+        ));
+    }
+    interpreter.set_process_access(true);
+    assert!(interpreter.eval_str(r#"getenv "HOME""#).is_ok());
+    interpreter.set_process_access(false);
+    assert!(matches!(
+        interpreter.eval_str(r#"getenv "HOME""#),
+        Err(SatukitanError::UndefinedSymbol(_))
+    ));
+}
+
+#[test]
+fn process_access_survives_a_reset() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_process_access(true);
+    interpreter.reset();
+    assert!(matches!(
+        interpreter.eval_str("exit ru"),
+        Err(SatukitanError::Exit(1))
+    ));
+}
+
+#[test]
+fn getenv_reads_variables_and_returns_nil_when_unset() {
+    let expected = std::env::var("CARGO_PKG_NAME").unwrap();
+    let result = eval_with_process(r#"getenv "CARGO_PKG_NAME""#).unwrap();
+    assert!(matches!(result, Value::String(ref s) if **s == *expected));
+    let missing = eval_with_process(r#"getenv "SATUKITAN_SURELY_UNSET_VARIABLE""#).unwrap();
+    assert!(missing.is_nil());
+}
+
+#[test]
+fn exit_stops_evaluation_with_a_code() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        interpreter.set_process_access(true);
+        let err = interpreter
+            .eval_str("(gakas x ru) (exit ro) (gakas x ra)")
+            .unwrap_err();
+        assert!(matches!(err, SatukitanError::Exit(3)), "{backend:?}");
+        assert!(matches!(
+            interpreter.eval_str("x").unwrap(),
+            Value::Number(1)
+        ));
+    }
+    assert!(matches!(
+        eval_with_process("(exit)"),
+        Err(SatukitanError::Exit(0))
+    ));
+    assert!(matches!(
+        eval_with_process("exit (nitas #ta #ta ro)"),
+        Err(SatukitanError::Eval(_))
+    ));
+}
+
+#[test]
+fn cli_passes_trailing_args_and_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("main.st");
+    fs::write(
+        &script,
+        "(sipus args)\n(exit (rakas args))\n(sipus \"unreachable\")\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("run")
+        .arg(&script)
+        .args(["a", "--flag", "b"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "[a --flag b]\n");
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use rustyline::Context;
+use rustyline::completion::Completer;
+use rustyline::history::DefaultHistory;
+use satukitan_rs::Interpreter;
+use satukitan_rs::repl::SatukitanHelper;
+
+fn run_repl(input: &str) -> String {
+    let data = tempfile::tempdir().unwrap();
+    run_repl_in(data.path(), input)
+}
+
+/// Runs a REPL session whose history lives under `data`.
+fn run_repl_in(data: &Path, input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_satukitan"))
+        .arg("repl")
+        .env("HOME", data)
+        .env("XDG_DATA_HOME", data)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn multi_line_definitions_are_read_until_brackets_close() {
+    let input = "gakasdenu add-two (x y) (\n    ritas x y\n)\nadd-two ra ru\n";
+    assert_eq!(run_repl(input), "<lambda (x y)>\nro\n");
+}
+
+#[test]
+fn strings_may_span_lines() {
+    assert_eq!(run_repl("sipus \"a\nb\"\n"), "a\nb\n");
+}
+
+fn completions(interpreter: &Interpreter, line: &str) -> Vec<String> {
+    let helper = SatukitanHelper::new(interpreter);
+    let history = DefaultHistory::new();
+    let (_, pairs) = helper
+        .complete(line, line.len(), &Context::new(&history))
+        .unwrap();
+    pairs.into_iter().map(|pair| pair.display).collect()
+}
+
+#[test]
+fn completion_includes_live_bindings() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("gakasdenu add-two (x y) (ritas x y)\ngakas greeting \"sana\"")
+        .unwrap();
+    assert_eq!(completions(&interpreter, "(add"), ["add-two(x y)"]);
+    assert_eq!(completions(&interpreter, "gree"), ["greeting(string)"]);
+    assert_eq!(
+        completions(&interpreter, "ma"),
+        ["matyes(num num -> num)", "max(a b)"]
+    );
+}
+
+#[test]
+fn completion_follows_later_definitions() {
+    let mut interpreter = Interpreter::bare();
+    let helper = SatukitanHelper::new(&interpreter);
+    let history = DefaultHistory::new();
+    let context = Context::new(&history);
+    assert!(helper.complete("tot", 3, &context).unwrap().1.is_empty());
+    interpreter.eval_str("gakas total [ru]").unwrap();
+    let (start, pairs) = helper.complete("(tot", 4, &context).unwrap();
+    assert_eq!(start, 1);
+    assert_eq!(pairs[0].replacement, "total");
+    assert_eq!(pairs[0].display, "total(list)");
+}
+
+#[test]
+fn meta_commands_inspect_the_session() {
+    let input =
+        "gakasdenu add-two (x y) (ritas x y)\ngakas x ra\n:env\n:type add-two\n:type sipus\n";
+    let output = run_repl(input);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"add-two : function (x y)"));
+    assert!(lines.contains(&"x : number = ra"));
+    assert!(lines.contains(&"max : function (a b)"));
+    assert!(!lines.iter().any(|line| line.starts_with("ritas")));
+    assert_eq!(lines[lines.len() - 1], "sipus : builtin (value -> nil)");
+}
+
+#[test]
+fn meta_commands_load_reset_and_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let lib = dir.path().join("lib.st");
+    std::fs::write(&lib, "gakas from-file ro\n").unwrap();
+    let input = format!(
+        ":load {}\nfrom-file\n:time ritas ra ru\n:reset\nsipus \"after\"\nfrom-file\n",
+        lib.display()
+    );
+    let output = run_repl(&input);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..3], ["ro", "ro", "ro"]);
+    assert!(lines[3].contains("time: "));
+    assert_eq!(lines[4..], ["after"]);
+}
+
+#[test]
+fn ast_shows_the_parsed_expression() {
+    let output = run_repl(":ast ritas ra ru\n");
+    assert!(output.starts_with("Call {\n    func: Symbol(\n        \"ritas\",\n    ),"));
+}
+
+#[test]
+fn history_persists_across_sessions() {
+    let data = tempfile::tempdir().unwrap();
+    run_repl_in(data.path(), "gakas x ra\n:env\n");
+    run_repl_in(data.path(), "ritas ru ru\n");
+    let history = fs::read_to_string(data.path().join("satukitan/history.txt")).unwrap();
+    let entries = history.lines().filter(|line| !line.starts_with('#'));
+    assert_eq!(
+        entries.collect::<Vec<_>>(),
+        ["gakas x ra", ":env", "ritas ru ru"]
+    );
+}
+
+#[test]
+fn sessions_can_be_saved_and_restored() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("session.st");
+    let input = format!(
+        "gakasdenu add-two (x y) (\n    ritas x y\n)\nundefined-name\n:type add-two\ngakas x (add-two ra ru)\n:save {}\n",
+        script.display()
+    );
+    let output = run_repl(&input);
+    assert!(output.ends_with(&format!("saved 2 inputs to {}\n", script.display())));
+    assert_eq!(
+        fs::read_to_string(&script).unwrap(),
+        "gakasdenu add-two (x y) (\n    ritas x y\n)\ngakas x (add-two ra ru)\n"
+    );
+
+    let output = run_repl(&format!("gakas x ru\n:restore {}\nx\n", script.display()));
+    assert_eq!(output, "ru\nro\nro\n");
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::eval;
+use satukitan_rs::env::Environment;
+use satukitan_rs::parser::parse_program;
+use satukitan_rs::resolver::{Node, resolve_program};
+use satukitan_rs::symbol::Symbol;
+use satukitan_rs::value::Value;
+
+#[test]
+fn symbols_are_interned() {
+    assert_eq!(Symbol::intern("fibo"), Symbol::intern("fibo"));
+    assert_ne!(Symbol::intern("fibo"), Symbol::intern("fibo-2"));
+    assert_eq!(Symbol::intern("matyes").as_str(), "matyes");
+}
+
+#[test]
+fn looking_up_a_name_does_not_intern_it() {
+    let interpreter = satukitan_rs::Interpreter::new();
+    assert_eq!(Symbol::lookup("gatas"), Some(Symbol::intern("gatas")));
+    let env = interpreter.environment();
+    assert!(env.borrow().get("never-defined-anywhere").is_none());
+    assert!(
+        env.borrow_mut()
+            .assign("never-assigned-anywhere", Value::Nil)
+            .is_err()
+    );
+    assert_eq!(Symbol::lookup("never-defined-anywhere"), None);
+    assert_eq!(Symbol::lookup("never-assigned-anywhere"), None);
+}
+
+#[test]
+fn function_body_resolves_to_slots() {
+    let program = parse_program("gakasdenu f (a b) (ritas b x)").expect("parse failed");
+    let mut env = Environment::new();
+    let nodes = resolve_program(&program, &mut env);
+    let lambda = match &nodes[0] {
+        Node::Gakasdenu { lambda, .. } => lambda.clone(),
+        other => panic!("expected gakasdenu, got {:?}", other),
+    };
+    match &lambda.body[0] {
+        Node::Apply { callee, args } => {
+            assert!(matches!(callee.as_ref(), Node::Var { depth: 1, .. }));
+            assert!(matches!(
+                args[0],
+                Node::Var {
+                    depth: 0,
+                    slot: 1,
+                    ..
+                }
+            ));
+            assert!(matches!(args[1], Node::Var { depth: 1, .. }));
+        }
+        other => panic!("expected application, got {:?}", other),
+    }
+}
+
+#[test]
+fn local_binding_shadows_only_after_definition() {
+    let source = r#"
+        gakas x ru
+        gakasdenu f () (
+            (gakas before x)
+            (gakas x ra)
+            (ritas before x)
+        )
+        ritas (f) x
+    "#;
+    let result = eval(source).expect("evaluation should succeed");
+    assert!(matches!(result, Value::Number(4)));
+}
+
+#[test]
+fn closures_capture_enclosing_frame() {
+    let source = r#"
+        gakasdenu adder (n) (
+            (gakasdenu add (x) (ritas n x))
+            add
+        )
+        gakas add-three (adder ro)
+        add-three re
+    "#;
+    let result = eval(source).expect("evaluation should succeed");
+    assert!(matches!(result, Value::Number(7)));
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "serde")]
+
+use satukitan_rs::Interpreter;
+use satukitan_rs::ast::Expr;
+use satukitan_rs::parser::parse_program;
+use satukitan_rs::value::Value;
+
+#[test]
+fn program_round_trips_through_json() {
+    let source = r#"
+        gakasdenu fibo (n) (nobu (ditasgata n ru) (n) (ritas (fibo (matyes n ru)) (fibo (matyes n ra))))
+        sipus "fibo" [ru me] (fibo #ta)
+    "#;
+    let program = parse_program(source).unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+    let decoded: Vec<Expr> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, program);
+}
+
+#[test]
+fn data_values_serialize_to_plain_json() {
+    let value = Interpreter::new()
+        .eval_str(r#"[ru me "x" [ra] (fanitas [ro ru])]"#)
+        .unwrap();
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"[1,true,"x",[2],[1,3]]"#);
+    assert_eq!(serde_json::to_string(&Value::Nil).unwrap(), "null");
+
+    let decoded: Value = serde_json::from_str(&json).unwrap();
+    assert!(decoded.structural_eq(&value));
+    assert!(serde_json::from_str::<Value>("null").unwrap().is_nil());
+}
+
+#[test]
+fn callables_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    let function = interpreter.eval_str("gakasdenu f (x) (x)\nf").unwrap();
+    let err = serde_json::to_string(&function).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("cannot serialize a function value")
+    );
+
+    let builtin = interpreter.environment().borrow().get("ritas").unwrap();
+    let err = serde_json::to_string(&vec![builtin]).unwrap_err();
+    assert!(err.to_string().contains("cannot serialize a builtin value"));
+}
+
+#[test]
+fn unsupported_json_is_rejected() {
+    assert!(serde_json::from_str::<Value>("1.5").is_err());
+    assert!(serde_json::from_str::<Value>(r#"{"a": 1}"#).is_err());
+    assert!(serde_json::from_str::<Value>("18446744073709551615").is_err());
+}
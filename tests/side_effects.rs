@@ -1,11 +1,52 @@
+mod common;
+
+use common::{BACKENDS, run};
 use satukitan_rs::Interpreter;
+use satukitan_rs::io::OutputBuffer;
 use satukitan_rs::value::Value;
+
+fn capture(source: &str) -> (Value, String) {
+    let run = run(source);
+    (run.result.expect("evaluation should succeed"), run.output)
+}
+
 #[test]
 fn nested_list_evaluates_sequentially() {
-    let mut interpreter = Interpreter::new();
-    let source = "sipus matyes ra ru";
-    let result = interpreter
-        .eval_str(source)
-        .expect("evaluation should succeed");
+    let (result, output) = capture("sipus matyes ra ru");
     assert!(matches!(result, Value::Nil));
+    assert_eq!(output, "ru\n");
+}
+
+#[test]
+fn sipus_joins_arguments_with_spaces() {
+    let (_, output) = capture(r#"(sipus "ritas" ru [ra ro])"#);
+    assert_eq!(output, "ritas ru [ra ro]\n");
+}
+
+#[test]
+fn sipus_without_arguments_prints_empty_line() {
+    let (_, output) = capture("(sipus)");
+    assert_eq!(output, "\n");
+}
+
+#[test]
+fn output_is_written_in_evaluation_order() {
+    let (_, output) = capture("(sipus ru) (sipus ra) (sipus ritas ra ro)");
+    assert_eq!(output, "ru\nra\nri\n");
+}
+
+#[test]
+fn output_can_be_redirected_between_evaluations() {
+    for backend in BACKENDS {
+        let mut interpreter = Interpreter::with_backend(backend);
+        let first = OutputBuffer::new();
+        let second = OutputBuffer::new();
+        interpreter.set_output(first.clone());
+        interpreter.eval_str("(sipus ru)").unwrap();
+        interpreter.set_output(second.clone());
+        interpreter.eval_str("(sipus ra)").unwrap();
+        assert_eq!(first.take(), "ru\n");
+        assert_eq!(second.contents(), "ra\n");
+        assert_eq!(first.contents(), "");
+    }
 }